@@ -0,0 +1,548 @@
+use crate::*;
+use chrono::prelude::*;
+use log::*;
+use rusqlite::{Connection, params};
+use serde_json::json;
+use sha1::{Digest, Sha1};
+use std::{
+    fs::File,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// collections in .apkg or .colpkg files, newer ones first.
+/// Newer Anki puts a dummy note into `collection.anki2` besides the real `collection.anki21`.
+const COLLECTIONS: [&str; 2] = ["collection.anki21", "collection.anki2"];
+
+/// the note type of exported items, fixed so that Anki recognizes it in later exports.
+const MODEL_ID: i64 = 1_718_000_000_000;
+
+const SCHEMA: &str = "
+CREATE TABLE col (
+    id integer primary key, crt integer not null, mod integer not null,
+    scm integer not null, ver integer not null, dty integer not null,
+    usn integer not null, ls integer not null, conf text not null,
+    models text not null, decks text not null, dconf text not null,
+    tags text not null);
+CREATE TABLE notes (
+    id integer primary key, guid text not null, mid integer not null,
+    mod integer not null, usn integer not null, tags text not null,
+    flds text not null, sfld integer not null, csum integer not null,
+    flags integer not null, data text not null);
+CREATE TABLE cards (
+    id integer primary key, nid integer not null, did integer not null,
+    ord integer not null, mod integer not null, usn integer not null,
+    type integer not null, queue integer not null, due integer not null,
+    ivl integer not null, factor integer not null, reps integer not null,
+    lapses integer not null, left integer not null, odue integer not null,
+    odid integer not null, flags integer not null, data text not null);
+CREATE TABLE revlog (
+    id integer primary key, cid integer not null, usn integer not null,
+    ease integer not null, ivl integer not null, lastIvl integer not null,
+    factor integer not null, time integer not null, type integer not null);
+CREATE TABLE graves (usn integer not null, oid integer not null, type integer not null);
+CREATE INDEX ix_notes_usn on notes (usn);
+CREATE INDEX ix_cards_usn on cards (usn);
+CREATE INDEX ix_revlog_usn on revlog (usn);
+CREATE INDEX ix_cards_nid on cards (nid);
+CREATE INDEX ix_cards_sched on cards (did, queue, due);
+CREATE INDEX ix_revlog_cid on revlog (cid);
+CREATE INDEX ix_notes_csum on notes (csum);
+";
+
+/// Reads items from an Anki .apkg or .colpkg file.
+///
+/// The first two fields of notes are questions and answers.
+/// Scheduling follows the first card of every note:
+/// reviews since the last lapse tell when it is remembered,
+/// and the due day of review cards tells when it is due.
+/// Buried cards are buried until the next day.
+pub fn read_anki(file_name: &Path, keep_html: bool) -> Vec<Item> {
+    let temp = temp_collection();
+    {
+        let mut archive = zip::ZipArchive::new(File::open(file_name).unwrap()).unwrap();
+        let Some(name) = COLLECTIONS
+            .into_iter()
+            .find(|x| archive.index_for_name(x).is_some())
+        else {
+            if archive.index_for_name("collection.anki21b").is_some() {
+                panic!(
+                    "unsupported format of the latest Anki. Please export with \"Support older Anki versions\": {}",
+                    file_name.display()
+                );
+            }
+            panic!("no Anki collections in {}", file_name.display());
+        };
+        debug!("read {} in {}", name, file_name.display());
+        let mut src = archive.by_name(name).unwrap();
+        let mut dst = File::create(&temp).unwrap();
+        std::io::copy(&mut src, &mut dst).unwrap();
+    }
+
+    let conn = Connection::open(&temp).unwrap();
+    let crt: i64 = conn
+        .query_row("SELECT crt FROM col", [], |r| r.get(0))
+        .unwrap();
+    let crt = DateTime::from_timestamp(crt, 0).unwrap();
+    let mut notes = conn
+        .prepare(
+            "SELECT n.flds, n.tags, c.id, c.type, c.due, c.reps, c.lapses, c.queue FROM notes n
+             LEFT JOIN cards c ON c.nid = n.id AND c.ord = 0
+             ORDER BY n.id",
+        )
+        .unwrap();
+    let mut reviews = conn
+        .prepare("SELECT id, ease FROM revlog WHERE cid = ?1 AND ease > 0 ORDER BY id")
+        .unwrap();
+    let mut res = vec![];
+    let mut rows = notes.query([]).unwrap();
+    while let Some(row) = rows.next().unwrap() {
+        let fields: String = row.get(0).unwrap();
+        let tags: String = row.get(1).unwrap();
+        let card: Option<i64> = row.get(2).unwrap();
+        let mut fields = fields.split('\x1f').map(|x| {
+            if keep_html {
+                x.to_string()
+            } else {
+                strip_html(x)
+            }
+        });
+        let mut item = Item {
+            question: fields.next().unwrap_or_default(),
+            answer: fields.next().unwrap_or_default(),
+            first_remember_time: None,
+            last_check_time: None,
+            due_time: None,
+            tag: Some(tags.split_whitespace().collect::<Vec<_>>().join(" "))
+                .filter(|x| !x.is_empty()),
+            reviews: 0,
+            lapses: 0,
+            status: Status::Active,
+        };
+        if item.question.is_empty() {
+            warn!("skip a note without questions: {:?}", item);
+            continue;
+        }
+        if let Some(card) = card {
+            let mut revs = reviews.query([card]).unwrap();
+            while let Some(rev) = revs.next().unwrap() {
+                let time = DateTime::from_timestamp_millis(rev.get(0).unwrap()).unwrap();
+                let ease: i64 = rev.get(1).unwrap();
+                if ease == 1 {
+                    item.first_remember_time = None;
+                } else if item.first_remember_time.is_none() {
+                    item.first_remember_time = Some(time);
+                }
+                item.last_check_time = Some(time);
+            }
+            // as counted by Anki, since the review log may be trimmed
+            item.reviews = row.get(5).unwrap();
+            item.lapses = row.get(6).unwrap();
+            let queue: i64 = row.get(7).unwrap();
+            item.status = match queue {
+                -1 => Status::Suspended,
+                // buried by siblings or by users
+                -2 | -3 => {
+                    let boundary = DayBoundary::default();
+                    let now = Utc::now();
+                    Status::Buried(boundary.start(boundary.day(&now).succ_opt().unwrap()))
+                }
+                _ => Status::Active,
+            };
+            let kind: i64 = row.get(3).unwrap();
+            let due: i64 = row.get(4).unwrap();
+            item.due_time = match kind {
+                // reviews, due in days since the collection is created
+                2 => Some(crt + chrono::Duration::days(due)),
+                // (re)learning, due in seconds
+                1 | 3 => DateTime::from_timestamp(due, 0),
+                _ => None,
+            };
+        }
+        res.push(item);
+    }
+    drop(rows);
+    drop(notes);
+    drop(reviews);
+    conn.close().unwrap();
+    std::fs::remove_file(&temp).unwrap();
+    debug!("read {} items from {}.", res.len(), file_name.display());
+    res
+}
+
+/// Writes items into an Anki .apkg file, as notes of a deck.
+///
+/// Items with due times become review cards, and others new cards.
+/// Their check times are kept as reviews.
+pub fn write_anki(file_name: &Path, items: &[Item], deck: &str) {
+    debug!("write {} items into {}.", items.len(), file_name.display());
+    let now = Utc::now();
+    let crt = items
+        .iter()
+        .filter_map(|x| x.due_time)
+        .chain([now])
+        .min()
+        .unwrap()
+        .date_naive()
+        .and_hms_opt(0, 0, 0)
+        .unwrap()
+        .and_utc();
+    let deck_id = now.timestamp_millis();
+    let temp = temp_collection();
+    let mut conn = Connection::open(&temp).unwrap();
+    conn.execute_batch(SCHEMA).unwrap();
+    let tx = conn.transaction().unwrap();
+    tx.execute(
+        "INSERT INTO col VALUES (1, ?1, ?2, ?2, 11, 0, 0, 0, ?3, ?4, ?5, ?6, '{}')",
+        params![
+            crt.timestamp(),
+            now.timestamp_millis(),
+            conf(items.len()).to_string(),
+            models(deck_id, now.timestamp()).to_string(),
+            decks(deck_id, deck, now.timestamp()).to_string(),
+            dconf().to_string(),
+        ],
+    )
+    .unwrap();
+    let mut revlog_id = 0;
+    for (i, item) in items.iter().enumerate() {
+        let id = now.timestamp_millis() + i as i64;
+        let question = escape_html(&item.question);
+        let digest = Sha1::digest(item.question.as_bytes());
+        let guid: String = digest[..8].iter().map(|x| format!("{:02x}", x)).collect();
+        let csum = u32::from_be_bytes(digest[..4].try_into().unwrap());
+        let tags = match &item.tag {
+            Some(tag) => format!(" {} ", tag),
+            None => String::new(),
+        };
+        tx.execute(
+            "INSERT INTO notes VALUES (?1, ?2, ?3, ?4, -1, ?5, ?6, ?7, ?8, 0, '')",
+            params![
+                id,
+                guid,
+                MODEL_ID,
+                now.timestamp(),
+                tags,
+                format!("{}\x1f{}", question, escape_html(&item.answer)),
+                item.question,
+                csum,
+            ],
+        )
+        .unwrap();
+        let (kind, due, ivl) = match item.due_time {
+            Some(due) => {
+                let ivl = item
+                    .last_check_time
+                    .map(|x| (due - x).num_days())
+                    .unwrap_or(1)
+                    .max(1);
+                (2, (due - crt).num_days(), ivl)
+            }
+            None => (0, i as i64 + 1, 0),
+        };
+        let mut revs = vec![];
+        if let Some(first) = item.first_remember_time
+            && item.last_check_time != Some(first)
+        {
+            revs.push((first, 3));
+        }
+        if let Some(last) = item.last_check_time {
+            revs.push((
+                last,
+                if item.first_remember_time.is_some() {
+                    3
+                } else {
+                    1
+                },
+            ));
+        }
+        tx.execute(
+            "INSERT INTO cards VALUES (?1, ?1, ?2, 0, ?3, -1, ?4, ?5, ?6, ?7, 2500, ?8, ?9, 0, 0, 0, 0, '')",
+            params![
+                id,
+                deck_id,
+                now.timestamp(),
+                kind,
+                match item.status {
+                    Status::Active => kind,
+                    // buried by users
+                    Status::Buried(_) => -3,
+                    // Other items out of reviews are suspended in Anki.
+                    _ => -1,
+                },
+                due,
+                ivl,
+                (item.reviews as i64).max(revs.len() as i64),
+                item.lapses
+            ],
+        )
+        .unwrap();
+        for (time, ease) in revs.into_iter() {
+            // IDs of reviews are their times in milliseconds, which must be unique.
+            revlog_id = time.timestamp_millis().max(revlog_id + 1);
+            tx.execute(
+                "INSERT INTO revlog VALUES (?1, ?2, -1, ?3, ?4, 0, 2500, 0, 1)",
+                params![revlog_id, id, ease, ivl],
+            )
+            .unwrap();
+        }
+    }
+    tx.commit().unwrap();
+    conn.close().unwrap();
+
+    let mut zip = zip::ZipWriter::new(File::create(file_name).unwrap());
+    let options = zip::write::SimpleFileOptions::default();
+    zip.start_file("collection.anki2", options).unwrap();
+    zip.write_all(&std::fs::read(&temp).unwrap()).unwrap();
+    zip.start_file("media", options).unwrap();
+    zip.write_all(b"{}").unwrap();
+    zip.finish().unwrap();
+    std::fs::remove_file(&temp).unwrap();
+}
+
+/// a new temporary file of collections, unique in the process.
+fn temp_collection() -> PathBuf {
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+    let res = std::env::temp_dir().join(format!(
+        "memory-palace-{}-{}.anki2",
+        std::process::id(),
+        COUNT.fetch_add(1, Ordering::Relaxed)
+    ));
+    if res.exists() {
+        std::fs::remove_file(&res).unwrap();
+    }
+    res
+}
+
+/// Turns HTML of Anki fields into plain text, breaking lines at line breaks and blocks.
+fn strip_html(s: &str) -> String {
+    let mut res = String::new();
+    let mut rest = s;
+    while let Some(start) = rest.find('<') {
+        res.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let Some(len) = rest.find('>') else {
+            break;
+        };
+        let tag = rest[1..len]
+            .trim_start_matches('/')
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap()
+            .to_lowercase();
+        if matches!(tag.as_str(), "br" | "div" | "p" | "li") && !res.ends_with('\n') {
+            res.push('\n');
+        }
+        rest = &rest[len + 1..];
+    }
+    res.push_str(rest);
+    res.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+        .trim()
+        .to_string()
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('\n', "<br>")
+}
+
+fn conf(n: usize) -> serde_json::Value {
+    json!({
+        "nextPos": n + 1,
+        "estTimes": true,
+        "activeDecks": [1],
+        "sortType": "noteFld",
+        "timeLim": 0,
+        "sortBackwards": false,
+        "addToCur": true,
+        "curDeck": 1,
+        "newBury": true,
+        "newSpread": 0,
+        "dueCounts": true,
+        "curModel": MODEL_ID,
+        "collapseTime": 1200,
+    })
+}
+
+fn models(deck_id: i64, now: i64) -> serde_json::Value {
+    let field = |name: &str, ord: usize| {
+        json!({
+            "name": name,
+            "ord": ord,
+            "sticky": false,
+            "rtl": false,
+            "font": "Arial",
+            "size": 20,
+            "media": [],
+        })
+    };
+    json!({
+        MODEL_ID.to_string(): {
+            "id": MODEL_ID,
+            "name": "Memory Palace",
+            "type": 0,
+            "mod": now,
+            "usn": -1,
+            "sortf": 0,
+            "did": deck_id,
+            "tmpls": [{
+                "name": "Card 1",
+                "ord": 0,
+                "qfmt": "{{Question}}",
+                "afmt": "{{FrontSide}}<hr id=answer>{{Answer}}",
+                "bqfmt": "",
+                "bafmt": "",
+                "did": null,
+                "bfont": "",
+                "bsize": 0,
+            }],
+            "flds": [field("Question", 0), field("Answer", 1)],
+            "css": ".card { font-family: arial; font-size: 20px; text-align: center; color: black; background-color: white; }",
+            "latexPre": "\\documentclass[12pt]{article}\n\\special{papersize=3in,5in}\n\\usepackage[utf8]{inputenc}\n\\usepackage{amssymb,amsmath}\n\\pagestyle{empty}\n\\setlength{\\parindent}{0in}\n\\begin{document}\n",
+            "latexPost": "\\end{document}",
+            "latexsvg": false,
+            "req": [[0, "any", [0]]],
+            "tags": [],
+            "vers": [],
+        }
+    })
+}
+
+fn decks(deck_id: i64, name: &str, now: i64) -> serde_json::Value {
+    let deck = |id: i64, name: &str| {
+        json!({
+            "id": id,
+            "name": name,
+            "mod": now,
+            "usn": -1,
+            "lrnToday": [0, 0],
+            "revToday": [0, 0],
+            "newToday": [0, 0],
+            "timeToday": [0, 0],
+            "collapsed": false,
+            "browserCollapsed": false,
+            "desc": "",
+            "dyn": 0,
+            "conf": 1,
+            "extendNew": 0,
+            "extendRev": 0,
+        })
+    };
+    json!({
+        "1": deck(1, "Default"),
+        deck_id.to_string(): deck(deck_id, name),
+    })
+}
+
+fn dconf() -> serde_json::Value {
+    json!({
+        "1": {
+            "id": 1,
+            "name": "Default",
+            "mod": 0,
+            "usn": 0,
+            "maxTaken": 60,
+            "autoplay": true,
+            "timer": 0,
+            "replayq": true,
+            "dyn": false,
+            "new": {
+                "bury": false,
+                "delays": [1.0, 10.0],
+                "initialFactor": 2500,
+                "ints": [1, 4, 0],
+                "order": 1,
+                "perDay": 20,
+            },
+            "rev": {
+                "bury": false,
+                "ease4": 1.3,
+                "ivlFct": 1.0,
+                "maxIvl": 36500,
+                "perDay": 200,
+                "hardFactor": 1.2,
+            },
+            "lapse": {
+                "delays": [10.0],
+                "leechAction": 1,
+                "leechFails": 8,
+                "minInt": 1,
+                "mult": 0.0,
+            },
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strip_html_of_fields() {
+        assert_eq!(strip_html("plain"), "plain");
+        assert_eq!(strip_html("<b>bold</b> text"), "bold text");
+        assert_eq!(
+            strip_html("line 1<br>line 2<br/>line 3"),
+            "line 1\nline 2\nline 3"
+        );
+        assert_eq!(strip_html("<div>a</div><div>b</div>"), "a\nb");
+        assert_eq!(
+            strip_html("1 &lt; 2&nbsp;&amp;&nbsp;&quot;x&quot;"),
+            "1 < 2 & \"x\""
+        );
+        assert_eq!(strip_html("a <unclosed"), "a <unclosed");
+    }
+
+    #[test]
+    fn export_and_import() {
+        let check = Utc.with_ymd_and_hms(2024, 1, 2, 3, 4, 5).unwrap();
+        // Anki keeps days of due times.
+        let due = Utc.with_ymd_and_hms(2024, 1, 5, 0, 0, 0).unwrap();
+        let reviewed = Item {
+            first_remember_time: Some(check),
+            last_check_time: Some(check),
+            due_time: Some(due),
+            tag: Some("x y".to_string()),
+            reviews: 1,
+            ..Item::new("Q1 <&>?", "A1\nline 2")
+        };
+        let suspended = Item {
+            status: Status::Suspended,
+            ..Item::new("Q2?", "A2")
+        };
+        let buried = Item {
+            status: Status::Buried(check),
+            ..Item::new("Q3?", "A3")
+        };
+        let items = vec![
+            reviewed.clone(),
+            suspended.clone(),
+            Item::new("Q4?", "A4"),
+            buried,
+        ];
+
+        let path =
+            std::env::temp_dir().join(format!("memory-palace-anki-{}.apkg", std::process::id()));
+        write_anki(&path, &items, "test");
+        let res = read_anki(&path, false);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(res.len(), items.len());
+        assert_eq!(res[0], reviewed);
+        assert_eq!(res[1], suspended);
+        assert_eq!(res[2], items[2]);
+        assert_eq!(res[3].question, "Q3?");
+        let Status::Buried(until) = res[3].status else {
+            panic!("not buried: {:?}", res[3].status);
+        };
+        assert!(until > Utc::now());
+    }
+}
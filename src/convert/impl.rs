@@ -0,0 +1,25 @@
+use crate::*;
+use log::*;
+use std::path::PathBuf;
+
+/// Converts a memory-palace file into another format or store, told by extensions.
+pub struct Convert {
+    /// the file to convert from
+    pub input: PathBuf,
+
+    /// the file to convert into
+    pub output: PathBuf,
+}
+
+impl Convert {
+    pub fn gogogo(&self) {
+        let items = open_store(&self.input).load();
+        info!(
+            "convert {} items from {} into {}.",
+            items.len(),
+            self.input.display(),
+            self.output.display()
+        );
+        open_store(&self.output).save(&items);
+    }
+}
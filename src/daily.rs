@@ -0,0 +1,243 @@
+use crate::*;
+use chrono::prelude::*;
+use log::*;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// the hidden file keeping how many items are examined today.
+pub const STATE_FILE: &str = ".palace-state.toml";
+
+/// When a day begins in the time zone of the user.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct DayBoundary {
+    /// the time zone, or the one of the system if `None`
+    pub zone: Option<chrono_tz::Tz>,
+
+    /// the local hour when a day begins, from 0 to 23
+    pub starts_at: u32,
+}
+
+/// How many items to examine every day at most.
+#[derive(Debug, Clone, Default)]
+pub struct DailyLimits {
+    /// new items, which were never checked
+    pub new_per_day: Option<usize>,
+
+    /// items checked before
+    pub reviews_per_day: Option<usize>,
+
+    pub boundary: DayBoundary,
+}
+
+/// How many items are examined in the day, across sessions.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DailyState {
+    pub day: NaiveDate,
+    pub new: usize,
+    pub reviews: usize,
+}
+
+impl DailyLimits {
+    pub fn is_limited(&self) -> bool {
+        self.new_per_day.is_some() || self.reviews_per_day.is_some()
+    }
+
+    /// Holds back candidates over the limits of the day,
+    /// keeping new items in their order and the most overdue items first.
+    ///
+    /// `item` tells the item of a candidate.
+    /// Items held back are untouched, so they are still due the next day.
+    pub fn hold_back<'a, T>(
+        &self,
+        candidates: Vec<T>,
+        item: impl Fn(&T) -> &'a Item,
+        state: &DailyState,
+    ) -> Vec<T> {
+        let total = candidates.len();
+        let (new, mut reviews): (Vec<_>, Vec<_>) = candidates
+            .into_iter()
+            .partition(|x| item(x).due_time.is_none());
+        reviews.sort_by_key(|x| item(x).due_time);
+        let mut res = vec![];
+        let new_left = self.new_per_day.map(|n| n.saturating_sub(state.new));
+        res.extend(new.into_iter().take(new_left.unwrap_or(usize::MAX)));
+        let reviews_left = self
+            .reviews_per_day
+            .map(|n| n.saturating_sub(state.reviews));
+        res.extend(reviews.into_iter().take(reviews_left.unwrap_or(usize::MAX)));
+        if res.len() < total {
+            info!(
+                "{} items held back by daily limits, {} new and {} reviews examined today.",
+                total - res.len(),
+                state.new,
+                state.reviews
+            );
+        }
+        res
+    }
+}
+
+impl DayBoundary {
+    /// the day of `time`, which rolls over at `starts_at` in local time.
+    pub fn day(&self, time: &UtcTime) -> NaiveDate {
+        let shift = chrono::Duration::hours(self.starts_at as i64);
+        // shifted in local time, which may skip or repeat hours
+        let local = match self.zone {
+            Some(zone) => time.with_timezone(&zone).naive_local(),
+            None => time.with_timezone(&Local).naive_local(),
+        };
+        (local - shift).date()
+    }
+
+    /// the time when `day` begins.
+    pub fn start(&self, day: NaiveDate) -> UtcTime {
+        let start = day.and_hms_opt(self.starts_at, 0, 0).unwrap();
+        match self.zone {
+            Some(zone) => local_to_utc(&zone, start),
+            None => local_to_utc(&Local, start),
+        }
+    }
+
+    /// Rounds `time` down to the beginning of its day.
+    pub fn floor(&self, time: &UtcTime) -> UtcTime {
+        self.start(self.day(time))
+    }
+
+    /// Rounds `time` down to the beginning of its day,
+    /// but not before the day after the one of `now`.
+    ///
+    /// A day longer than 24 hours, as when daylight saving time ends,
+    /// may hold both `now` and a day later.
+    pub fn floor_after(&self, time: &UtcTime, now: &UtcTime) -> UtcTime {
+        let next = self.start(self.day(now).succ_opt().unwrap());
+        self.floor(time).max(next)
+    }
+}
+
+/// Converts a local time to UTC, taking the earlier one if ambiguous,
+/// or the time an hour later if it is skipped by daylight saving time.
+fn local_to_utc<Tz: TimeZone>(zone: &Tz, time: NaiveDateTime) -> UtcTime {
+    match zone.from_local_datetime(&time).earliest() {
+        Some(x) => x.to_utc(),
+        None => local_to_utc(zone, time + chrono::Duration::hours(1)),
+    }
+}
+
+/// Parses time zones like `Asia/Tokyo`, or `local` for the one of the system.
+pub fn parse_zone(s: &str) -> Result<Option<chrono_tz::Tz>, String> {
+    if s == "local" {
+        return Ok(None);
+    }
+    s.parse()
+        .map(Some)
+        .map_err(|_| format!("unknown time zone: {s}"))
+}
+
+impl DailyState {
+    /// Reads the state of the day, which is empty if the file is missing or
+    /// of another day.
+    pub fn read(path: &Path, day: NaiveDate) -> Self {
+        let empty = Self {
+            day,
+            new: 0,
+            reviews: 0,
+        };
+        if !path.is_file() {
+            return empty;
+        }
+        debug!("read daily state {}", path.display());
+        let content = std::fs::read_to_string(path).unwrap();
+        let state: Self = toml::from_str(&content).unwrap();
+        if state.day == day { state } else { empty }
+    }
+
+    pub fn write(&self, path: &Path) {
+        debug!("write daily state {}", path.display());
+        std::fs::write(path, toml::to_string(self).unwrap()).unwrap();
+    }
+
+    /// Counts examined items, which are new if never checked before.
+    pub fn count<'a>(&mut self, items: impl IntoIterator<Item = &'a Item>) {
+        for x in items.into_iter() {
+            if x.due_time.is_none() {
+                self.new += 1;
+            } else {
+                self.reviews += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn berlin(starts_at: u32) -> DayBoundary {
+        DayBoundary {
+            zone: Some(chrono_tz::Europe::Berlin),
+            starts_at,
+        }
+    }
+
+    fn utc(y: i32, m: u32, d: u32, h: u32, min: u32) -> UtcTime {
+        Utc.with_ymd_and_hms(y, m, d, h, min, 0).unwrap()
+    }
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn day_across_dst() {
+        let boundary = berlin(4);
+        // 03:30 in winter time, before the day begins
+        assert_eq!(boundary.day(&utc(2024, 3, 31, 1, 30)), date(2024, 3, 30));
+        // 04:30 in summer time
+        assert_eq!(boundary.day(&utc(2024, 3, 31, 2, 30)), date(2024, 3, 31));
+        // 03:30 in summer time, before the day begins
+        assert_eq!(boundary.day(&utc(2024, 10, 27, 0, 30)), date(2024, 10, 26));
+        // 04:30 in winter time
+        assert_eq!(boundary.day(&utc(2024, 10, 27, 3, 30)), date(2024, 10, 27));
+    }
+
+    #[test]
+    fn start_across_dst() {
+        let boundary = berlin(4);
+        assert_eq!(boundary.start(date(2024, 3, 30)), utc(2024, 3, 30, 3, 0));
+        assert_eq!(boundary.start(date(2024, 3, 31)), utc(2024, 3, 31, 2, 0));
+        assert_eq!(boundary.start(date(2024, 10, 27)), utc(2024, 10, 27, 3, 0));
+    }
+
+    #[test]
+    fn start_of_skipped_or_ambiguous_hours() {
+        // 02:00 is skipped on 2024-03-31, and the day begins at 03:00.
+        assert_eq!(berlin(2).start(date(2024, 3, 31)), utc(2024, 3, 31, 1, 0));
+        // 02:00 happens twice on 2024-10-27, and the day begins at the earlier.
+        assert_eq!(berlin(2).start(date(2024, 10, 27)), utc(2024, 10, 27, 0, 0));
+    }
+
+    #[test]
+    fn floor_is_start_of_day() {
+        let boundary = berlin(4);
+        let time = utc(2024, 3, 31, 12, 0);
+        assert_eq!(boundary.floor(&time), utc(2024, 3, 31, 2, 0));
+        assert_eq!(
+            boundary.floor(&boundary.floor(&time)),
+            boundary.floor(&time)
+        );
+    }
+
+    #[test]
+    fn floor_after_long_day() {
+        let boundary = berlin(0);
+        // 00:30 in summer time on the day of 25 hours
+        let now = utc(2024, 10, 26, 22, 30);
+        let time = now + chrono::Duration::days(1);
+        assert_eq!(boundary.floor(&time), utc(2024, 10, 26, 22, 0));
+        assert_eq!(boundary.floor_after(&time, &now), utc(2024, 10, 27, 23, 0));
+        // the same as `floor` on other days
+        let now = utc(2024, 10, 28, 22, 30);
+        let time = now + chrono::Duration::days(2);
+        assert_eq!(boundary.floor_after(&time, &now), boundary.floor(&time));
+    }
+}
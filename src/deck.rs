@@ -0,0 +1,143 @@
+use crate::{merge::*, print::*, *};
+use log::*;
+
+/// Items of a memory palace in memory.
+///
+/// Operations return results instead of reading or writing files, so that
+/// programs can use memory palaces as a library.
+/// Commands load decks from stores, and save them back.
+#[derive(Debug, Clone, Default)]
+pub struct Deck {
+    pub items: Vec<Item>,
+
+    /// how to schedule items after reviews
+    pub scheduler: Scheduler,
+}
+
+/// What happens to items appended to a deck.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Appended {
+    /// items of new questions
+    pub added: usize,
+    /// items same as existing ones
+    pub skipped: usize,
+    /// items merged into existing ones of the same questions
+    pub updated: usize,
+}
+
+/// How well an item is remembered in a review.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rating {
+    Correct,
+    Wrong,
+}
+
+impl Deck {
+    pub fn new(items: Vec<Item>) -> Self {
+        Self {
+            items,
+            scheduler: Scheduler::default(),
+        }
+    }
+
+    pub fn load<S: Store + ?Sized>(store: &S) -> Self {
+        Self::new(store.load())
+    }
+
+    pub fn save<S: Store + ?Sized>(&self, store: &S) {
+        store.save(&self.items);
+    }
+
+    /// Items to review now, as [`Item::is_due`].
+    pub fn due(&self, now: &UtcTime) -> Deck {
+        self.select(|x| x.is_due(now))
+    }
+
+    /// Items meeting the conditions.
+    pub fn filter(&self, filter: &Filter, now: &UtcTime) -> Deck {
+        self.select(|x| filter.matches(x, now))
+    }
+
+    /// The item of the question.
+    pub fn get(&self, question: &str) -> Option<&Item> {
+        self.items.iter().find(|x| x.question == question)
+    }
+
+    /// Reviews the item of the question, and returns it as rescheduled.
+    pub fn review(&mut self, question: &str, rating: Rating, now: &UtcTime) -> Option<Item> {
+        let Some(item) = self.items.iter_mut().find(|x| x.question == question) else {
+            warn!("No item to review: {}", question);
+            return None;
+        };
+        match rating {
+            Rating::Correct => item.correct_with(now, &self.scheduler),
+            Rating::Wrong => item.wrong_with(now, &self.scheduler),
+        }
+        Some(item.clone())
+    }
+
+    /// Merges items of another deck into a new one, as `append` does.
+    pub fn merge(&self, other: &Deck) -> Deck {
+        let mut res = self.clone();
+        res.append(other.items.clone());
+        res
+    }
+
+    /// Appends items, where ones of existing questions are merged.
+    ///
+    /// As `update` without a base, contents of appended items win,
+    /// and scheduling follows the side checked later.
+    /// Duplicate questions among appended items are merged as well.
+    pub fn append(&mut self, items: Vec<Item>) -> Appended {
+        let mut res = Appended::default();
+        for item in items.into_iter() {
+            // Contents of appended items win, so they never conflict.
+            let base = self.get(&item.question).cloned();
+            match self.merge_item(&item, base.as_ref()) {
+                None => {
+                    debug!("added: {}", item.question);
+                    self.items.push(item);
+                    res.added += 1;
+                }
+                Some(Merged::Unchanged) => {
+                    debug!("skipped: {}", item.question);
+                    res.skipped += 1;
+                }
+                Some(_) => {
+                    debug!("updated: {}", item.question);
+                    res.updated += 1;
+                }
+            }
+        }
+        res
+    }
+
+    /// Merges an item into the one of the same question, against `base`,
+    /// the item before both sides changed, as [`merge`] does.
+    ///
+    /// The merged item replaces the one in the deck, unless they conflict.
+    /// Nothing is merged if the deck has no item of the question.
+    pub(crate) fn merge_item(&mut self, item: &Item, base: Option<&Item>) -> Option<Merged> {
+        let dest = self
+            .items
+            .iter_mut()
+            .find(|x| x.question == item.question)?;
+        let res = merge(dest, item, base);
+        if let Merged::Changed(x) = &res {
+            *dest = x.clone();
+        }
+        Some(res)
+    }
+
+    /// Prints items into a document in memory.
+    pub fn render(&self, format: &Format) -> Vec<u8> {
+        format.render(&self.items, &Header::default())
+    }
+
+    fn select(&self, pred: impl Fn(&Item) -> bool) -> Deck {
+        Deck {
+            items: self.items.iter().filter(|x| pred(x)).cloned().collect(),
+            scheduler: self.scheduler.clone(),
+        }
+    }
+}
@@ -2,7 +2,7 @@ use super::Selected;
 use eframe::egui;
 use egui::widget_text::RichText;
 use log::*;
-use std::{cell::RefCell, path::Path, rc::Rc, sync::Arc};
+use std::{cell::RefCell, path::PathBuf, rc::Rc, sync::Arc};
 
 pub struct App {
     selected: Rc<RefCell<Selected>>,
@@ -14,10 +14,15 @@ impl App {
         Self { selected }
     }
 
-    pub fn start(file_name: &Path, selected: Rc<RefCell<Selected>>) {
+    pub fn start(file_names: &[PathBuf], selected: Rc<RefCell<Selected>>) {
         let options = eframe::NativeOptions::default();
+        let title = file_names
+            .iter()
+            .map(|f| f.display().to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
         eframe::run_native(
-            &format!("{} - Memory Palace", file_name.display()),
+            &format!("{} - Memory Palace", title),
             options,
             Box::new(move |cc| Ok(Box::new(Self::new(&cc.egui_ctx, selected)))),
         )
@@ -62,9 +67,7 @@ fn setup_custom_fonts(ctx: &egui::Context) {
     let mut fonts = egui::FontDefinitions::default();
     fonts.font_data.insert(
         "cjk".to_owned(),
-        Arc::new(egui::FontData::from_static(include_bytes!(
-            "/usr/share/fonts/opentype/noto/NotoSansCJK-Regular.ttc"
-        ))),
+        Arc::new(egui::FontData::from_static(crate::CJK_FONT)),
     );
     if let Some(fs) = fonts.families.get_mut(&egui::FontFamily::Monospace) {
         fs.push("cjk".to_owned());
@@ -3,32 +3,87 @@ use chrono::prelude::*;
 use log::*;
 use std::{cell::RefCell, path::PathBuf, rc::Rc};
 
-pub struct Exam {
-    /// the file of a memory palace.
-    pub file_name: PathBuf,
+pub struct Exam<S: Store = Box<dyn Store>> {
+    /// the memory palaces to examine
+    pub workspace: Workspace<S>,
 
     /// Take at most N items of things to remember.
     pub take: Option<usize>,
 
+    /// Take at most N items from each file.
+    pub take_per_file: Option<usize>,
+
+    /// how to take items
+    pub sampling: Sampling,
+
     /// Do everything except writing back.
     pub dry_run: bool,
 
     /// Sort items
     pub sort: bool,
+
+    /// Examine only items meeting the conditions
+    pub filter: Filter,
 }
 
-impl Exam {
+impl<S: Store> Exam<S> {
     pub fn gogogo(&self) {
-        let items = read_file(&self.file_name);
+        let workspace = &self.workspace;
         let now = Utc::now();
-        let selected = exam::Selected::new(items, &now, self);
+        let decks: Vec<_> = workspace.files.iter().map(|f| f.due(&now)).collect();
+        let mut states = read_states(&workspace.files, &now);
+        let selected = exam::Selected::new(&workspace.files, decks, &now, &states, self);
         let selected = Rc::new(RefCell::new(selected));
-        exam::gui::App::start(&self.file_name, selected.clone());
+        exam::gui::App::start(&workspace.paths(), selected.clone());
+        let touched = selected.borrow().touched();
         let items = selected.borrow_mut().feedback(&now);
         if self.dry_run {
             info!("dry run!");
-        } else {
-            write_out(&self.file_name, &items);
+            return;
+        }
+        workspace.update(&items);
+        for (path, state) in states.iter_mut() {
+            state.count(
+                touched
+                    .iter()
+                    .filter(|x| workspace.files[x.0].state.as_ref() == Some(path))
+                    .map(|x| &x.1),
+            );
+            state.write(path);
+        }
+        if workspace.state.is_some() && !touched.is_empty() {
+            let session = Session::new(
+                workspace,
+                now,
+                touched
+                    .into_iter()
+                    .map(|(f, before)| SessionItem {
+                        file: std::fs::canonicalize(&workspace.files[f].path).unwrap(),
+                        after: items[f]
+                            .iter()
+                            .find(|x| x.question == before.question)
+                            .unwrap()
+                            .clone(),
+                        before,
+                    })
+                    .collect(),
+            );
+            session.write(workspace);
+            info!("session {} is recorded.", session.id);
+        }
+    }
+}
+
+/// Daily states of files with daily limits, each shared by files of the same manifest.
+fn read_states<S: Store>(files: &[PalaceFile<S>], now: &UtcTime) -> Vec<(PathBuf, DailyState)> {
+    let mut res: Vec<(PathBuf, DailyState)> = vec![];
+    for f in files.iter().filter(|x| x.limits.is_limited()) {
+        if let Some(path) = &f.state
+            && !res.iter().any(|x| &x.0 == path)
+        {
+            let day = f.limits.boundary.day(now);
+            res.push((path.clone(), DailyState::read(path, day)));
         }
     }
+    res
 }
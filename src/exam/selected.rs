@@ -1,75 +1,103 @@
-use crate::{Item, UtcTime};
 use super::r#impl::Exam;
+use crate::{DailyState, Deck, Item, PalaceFile, Rating, Store, UtcTime};
 use log::*;
-use rand::prelude::*;
+use std::path::PathBuf;
 
 pub struct Selected {
-    items: Vec<Item>,
-    selected_and_correctness: Vec<(usize, bool)>,
+    /// items to review of every file, in the order of files
+    decks: Vec<Deck>,
+
+    /// (index of file, index of item in the file, correctness)
+    selected_and_correctness: Vec<(usize, usize, bool)>,
 }
 
 impl Selected {
-    pub fn new(items: Vec<Item>, now: &UtcTime, cfg: &Exam) -> Self {
-        let mut selected_and_correctness: Vec<_> = items
-            .iter()
-            .enumerate()
-            .filter(|(_, x)| {
-                if let Some(due) = x.due_time {
-                    due < *now
-                } else {
-                    true
-                }
-            })
-            .map(|(i, _)| (i, true))
+    /// Selects items among `decks` of items due in every file.
+    pub fn new<S: Store>(
+        files: &[PalaceFile<S>],
+        decks: Vec<Deck>,
+        now: &UtcTime,
+        states: &[(PathBuf, DailyState)],
+        cfg: &Exam<S>,
+    ) -> Self {
+        let decks: Vec<_> = decks
+            .into_iter()
+            .map(|x| x.filter(&cfg.filter, now))
             .collect();
-        if cfg.sort {
-            selected_and_correctness.sort_by_key(|x| &items[x.0].question);
-        } else {
-            let mut rng = rand::rng();
-            selected_and_correctness.shuffle(&mut rng);
+        let items: Vec<_> = decks.iter().map(|x| &x.items).collect();
+        let mut selected_and_correctness = vec![];
+        for (f, deck) in decks.iter().enumerate() {
+            let mut selected: Vec<_> = (0..deck.items.len()).map(|i| (f, i, true)).collect();
+            if cfg.take_per_file.is_some() {
+                info!("sample items from {}.", files[f].path.display());
+                selected =
+                    cfg.sampling
+                        .sample(selected, |x| &items[x.0][x.1], cfg.take_per_file, now);
+            }
+            selected_and_correctness.append(&mut selected);
         }
-        if let Some(n) = cfg.take {
-            info!("{}/{} items selected.", n, selected_and_correctness.len());
-            selected_and_correctness.truncate(n);
-        } else {
-            info!(
-                "{}/{} items selected.",
-                selected_and_correctness.len(),
-                selected_and_correctness.len()
-            );
+        // Daily limits hold back items of files sharing the daily state.
+        let mut rest = selected_and_correctness;
+        let mut selected_and_correctness = vec![];
+        for (path, state) in states.iter() {
+            let (group, others): (Vec<_>, Vec<_>) = rest
+                .into_iter()
+                .partition(|x| files[x.0].state.as_ref() == Some(path));
+            rest = others;
+            let Some(first) = group.first() else {
+                continue;
+            };
+            let limits = &files[first.0].limits;
+            selected_and_correctness.extend(limits.hold_back(group, |x| &items[x.0][x.1], state));
+        }
+        selected_and_correctness.extend(rest);
+        let mut selected_and_correctness = cfg.sampling.sample(
+            selected_and_correctness,
+            |x| &items[x.0][x.1],
+            cfg.take,
+            now,
+        );
+        if cfg.sort {
+            selected_and_correctness.sort_by_key(|x| &items[x.0][x.1].question);
         }
         Self {
-            items,
+            decks,
             selected_and_correctness,
         }
     }
 
-    pub fn feedback(&mut self, now: &UtcTime) -> Vec<Item> {
-        let mut res = vec![];
-        std::mem::swap(&mut res, &mut self.items);
-        for (i, c) in self.selected_and_correctness.iter() {
-            if *c {
-                res[*i].correct(now);
-            } else {
-                res[*i].wrong(now);
-            }
+    /// Returns checked items of every file, in the order of files.
+    pub fn feedback(&mut self, now: &UtcTime) -> Vec<Vec<Item>> {
+        let mut res = vec![vec![]; self.decks.len()];
+        for (f, i, c) in self.selected_and_correctness.iter() {
+            let deck = &mut self.decks[*f];
+            let question = deck.items[*i].question.clone();
+            let rating = if *c { Rating::Correct } else { Rating::Wrong };
+            res[*f].extend(deck.review(&question, rating, now));
         }
-        res.sort_by_cached_key(|x| x.question.clone());
         res
     }
 
     pub fn items(&self) -> Vec<(Item, bool)> {
         self.selected_and_correctness
             .iter()
-            .map(|(x, c)| (self.items[*x].clone(), *c))
+            .map(|(f, i, c)| (self.decks[*f].items[*i].clone(), *c))
+            .collect()
+    }
+
+    /// Selected items before checked, with indices of their files.
+    pub fn touched(&self) -> Vec<(usize, Item)> {
+        self.selected_and_correctness
+            .iter()
+            .map(|(f, i, _)| (*f, self.decks[*f].items[*i].clone()))
             .collect()
     }
 
     pub fn set(&mut self, idx: usize) {
-        self.selected_and_correctness[idx].1 = true;
+        self.selected_and_correctness[idx].2 = true;
     }
 
     pub fn unset(&mut self, idx: usize) {
-        self.selected_and_correctness[idx].1 = false;
+        self.selected_and_correctness[idx].2 = false;
     }
 }
@@ -0,0 +1,37 @@
+use crate::*;
+use log::*;
+use std::path::PathBuf;
+
+pub struct Export {
+    /// the file, directory or workspace manifest of a memory palace
+    pub input: PathBuf,
+
+    /// the file to export into
+    pub output: PathBuf,
+
+    pub format: ExportFormat,
+}
+
+#[derive(Debug)]
+pub enum ExportFormat {
+    Csv(TableOptions),
+    Anki {
+        /// the name of the deck holding exported notes
+        deck: String,
+    },
+}
+
+impl Export {
+    pub fn gogogo(&self) {
+        let items: Vec<_> = Workspace::open(std::slice::from_ref(&self.input))
+            .read()
+            .into_iter()
+            .flatten()
+            .collect();
+        info!("export {} items.", items.len());
+        match &self.format {
+            ExportFormat::Csv(opts) => write_table(&self.output, &items, opts),
+            ExportFormat::Anki { deck } => write_anki(&self.output, &items, deck),
+        }
+    }
+}
@@ -2,49 +2,231 @@ use crate::*;
 use chrono::prelude::*;
 use log::*;
 use serde::{Deserialize, Serialize};
-use std::{io::Write, path::Path};
+use std::path::{Path, PathBuf};
+
+/// Formats of memory-palace files, told by their extensions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileFormat {
+    Toml,
+    Json,
+    Yaml,
+}
+
+impl FileFormat {
+    /// extensions of memory-palace files in directories
+    pub const EXTENSIONS: [&str; 4] = ["toml", "json", "yaml", "yml"];
+
+    /// the format of a file, or nothing if its extension is unknown.
+    pub fn of(file_name: &Path) -> Option<FileFormat> {
+        match file_name.extension().and_then(|x| x.to_str()) {
+            Some("toml") => Some(FileFormat::Toml),
+            Some("json") => Some(FileFormat::Json),
+            Some("yaml" | "yml") => Some(FileFormat::Yaml),
+            _ => None,
+        }
+    }
+
+    /// the format of a file, where files of unknown extensions are in TOML.
+    pub fn of_or_toml(file_name: &Path) -> FileFormat {
+        Self::of(file_name).unwrap_or(FileFormat::Toml)
+    }
+
+    /// the extension of files in the format.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            FileFormat::Toml => "toml",
+            FileFormat::Json => "json",
+            FileFormat::Yaml => "yaml",
+        }
+    }
+
+    /// Whether the content has items at the top level, told without parsing it,
+    /// so files like `Cargo.toml` or `package.json` are told apart cheaply.
+    pub fn has_items(&self, content: &str) -> bool {
+        match self {
+            FileFormat::Toml => {
+                for line in content.lines().map(|x| x.trim_start()) {
+                    if line.starts_with("[[items]]") {
+                        return true;
+                    }
+                    if line.starts_with('[') {
+                        // keys of tables
+                        return false;
+                    }
+                    if line
+                        .strip_prefix("items")
+                        .is_some_and(|x| x.trim_start().starts_with('='))
+                    {
+                        return true;
+                    }
+                }
+                false
+            }
+            FileFormat::Json => content.contains("\"items\""),
+            FileFormat::Yaml => content.lines().any(|x| x.starts_with("items:")),
+        }
+    }
+
+    fn parse(&self, content: &str) -> Result<Vec<Item>, String> {
+        match self {
+            FileFormat::Toml => {
+                let items: ItemsInDisk<serde::de::IgnoredAny> =
+                    toml::from_str(content).map_err(|e| e.to_string())?;
+                Ok(items.items.into_iter().map(|x| x.into()).collect())
+            }
+            FileFormat::Json => {
+                let items: ItemsInText<serde::de::IgnoredAny> =
+                    serde_json::from_str(content).map_err(|e| e.to_string())?;
+                Ok(items.items.into_iter().map(|x| x.into()).collect())
+            }
+            FileFormat::Yaml => {
+                let items: ItemsInText<serde::de::IgnoredAny> =
+                    serde_norway::from_str(content).map_err(|e| e.to_string())?;
+                Ok(items.items.into_iter().map(|x| x.into()).collect())
+            }
+        }
+    }
+
+    /// Formats items, keeping keys other than items in `old`, the content
+    /// of the file before, if any.
+    fn format(&self, items: &[Item], old: Option<&str>) -> String {
+        match self {
+            FileFormat::Toml => {
+                let mut rest: toml::Table =
+                    old.and_then(|x| toml::from_str(x).ok()).unwrap_or_default();
+                rest.remove("items");
+                let items = ItemsInDisk {
+                    items: items.iter().map(|x| x.into()).collect(),
+                    rest,
+                };
+                toml::to_string_pretty(&items).unwrap()
+            }
+            FileFormat::Json => {
+                let mut rest: serde_json::Map<String, serde_json::Value> = old
+                    .and_then(|x| serde_json::from_str(x).ok())
+                    .unwrap_or_default();
+                rest.remove("items");
+                let items = ItemsInText {
+                    items: items.iter().map(|x| x.into()).collect(),
+                    rest,
+                };
+                let mut res = serde_json::to_string_pretty(&items).unwrap();
+                res.push('\n');
+                res
+            }
+            FileFormat::Yaml => {
+                let mut rest: serde_norway::Mapping = old
+                    .and_then(|x| serde_norway::from_str(x).ok())
+                    .unwrap_or_default();
+                rest.remove("items");
+                let items = ItemsInText {
+                    items: items.iter().map(|x| x.into()).collect(),
+                    rest,
+                };
+                serde_norway::to_string(&items).unwrap()
+            }
+        }
+    }
+}
+
+/// A memory palace in a TOML, JSON or YAML file.
+#[derive(Debug, Clone)]
+pub struct FileStore {
+    pub path: PathBuf,
+}
+
+impl Store for FileStore {
+    fn load(&self) -> Vec<Item> {
+        read_file(&self.path)
+    }
+
+    fn save(&self, items: &[Item]) {
+        write_out(&self.path, items);
+    }
+
+    fn append(&self, items: Vec<Item>) -> Appended {
+        append(&self.path, items)
+    }
+}
 
 pub fn read_file(file_name: &Path) -> Vec<Item> {
-    let content = std::fs::read(file_name).unwrap();
-    let content = std::str::from_utf8(&content).unwrap();
-    let items: ItemsInDisk = toml::from_str(content).unwrap();
-    debug!(
-        "read {} items from {}.",
-        items.items.len(),
-        file_name.display()
-    );
-    items.items.into_iter().map(|x| x.into()).collect()
+    read_file_as(file_name, FileFormat::of_or_toml(file_name))
+}
+
+/// Whether a file of a known format has items, as [`FileFormat::has_items`].
+pub fn is_palace_file(file_name: &Path) -> bool {
+    let Some(format) = FileFormat::of(file_name) else {
+        return false;
+    };
+    std::fs::read_to_string(file_name).is_ok_and(|x| format.has_items(&x))
+}
+
+/// Reads items of a file in the format, whatever its extension is.
+pub fn read_file_as(file_name: &Path, format: FileFormat) -> Vec<Item> {
+    try_read_file_as(file_name, format)
+        .unwrap_or_else(|e| panic!("fail to read {}: {}", file_name.display(), e))
+}
+
+fn try_read_file_as(file_name: &Path, format: FileFormat) -> Result<Vec<Item>, String> {
+    let content = std::fs::read(file_name).map_err(|e| e.to_string())?;
+    let content = std::str::from_utf8(&content).map_err(|e| e.to_string())?;
+    let items = format.parse(content)?;
+    debug!("read {} items from {}.", items.len(), file_name.display());
+    Ok(items)
 }
 
+/// Writes items into a file, told its format by the extension.
+///
+/// Keys other than items in the file are kept.
 pub fn write_out(file_name: &Path, items: &[Item]) {
+    write_out_as(file_name, FileFormat::of_or_toml(file_name), items);
+}
+
+/// Writes items into a file in the format, whatever its extension is.
+pub fn write_out_as(file_name: &Path, format: FileFormat, items: &[Item]) {
     debug!("write {} items into {}.", items.len(), file_name.display());
-    let items: Vec<ItemInDisk> = items.iter().map(|x| x.into()).collect();
-    let items = ItemsInDisk { items };
-    let content = toml::to_string_pretty(&items).unwrap();
-    std::fs::write(file_name, content).unwrap();
+    let old = std::fs::read_to_string(file_name).ok();
+    let content = format.format(items, old.as_deref());
+    // Writes a hidden file beside, and renames it,
+    // so the file is never half written.
+    let name = file_name.file_name().unwrap().to_string_lossy();
+    let tmp = file_name.with_file_name(format!(".{}.tmp", name));
+    std::fs::write(&tmp, content).unwrap();
+    std::fs::rename(&tmp, file_name).unwrap();
 }
 
-pub fn append(file_name: &Path, items: Vec<Item>) {
+/// Appends items to a file, which is created if absent.
+///
+/// The file is loaded, merged with items as [`Deck::append`], and written
+/// back, so it stays a valid document without duplicate questions.
+pub fn append(file_name: &Path, items: Vec<Item>) -> Appended {
     debug!("append {} items into {}.", items.len(), file_name.display());
-    let items: Vec<ItemInDisk> = items.iter().map(|x| x.into()).collect();
-    let items = ItemsInDisk { items };
-    let content = toml::to_string_pretty(&items).unwrap();
-    let mut fp = std::fs::File::options()
-        .create(true)
-        .append(true)
-        .open(file_name)
-        .unwrap();
-    fp.write_all(content.as_bytes()).unwrap();
-    fp.write_all("\n".as_bytes()).unwrap();
+    let mut deck = if file_name.exists() {
+        Deck::new(read_file(file_name))
+    } else {
+        Deck::default()
+    };
+    let res = deck.append(items);
+    write_out(file_name, &deck.items);
+    res
+}
+
+/// Formats times as in memory-palace files.
+pub(crate) fn fmt_time(x: &Option<UtcTime>) -> String {
+    x.map(|x| x.format("%Y-%m-%dT%H:%M:%SZ").to_string())
+        .unwrap_or_default()
 }
 
+/// Items in TOML, with other keys as `R`, which are ignored in reading.
 #[derive(Debug, Clone, Serialize, Deserialize)]
-struct ItemsInDisk {
+struct ItemsInDisk<R = toml::Table> {
     items: Vec<ItemInDisk>,
+    #[serde(flatten)]
+    rest: R,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-struct ItemInDisk {
+pub(crate) struct ItemInDisk {
     #[serde(rename = "q")]
     question: String,
     #[serde(rename = "a")]
@@ -57,6 +239,96 @@ struct ItemInDisk {
     #[serde(rename = "due-time")]
     due_time: Option<toml::value::Datetime>,
     tag: Option<String>,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    reviews: u32,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    lapses: u32,
+    /// nothing if active
+    status: Option<String>,
+    #[serde(rename = "buried-until")]
+    buried_until: Option<toml::value::Datetime>,
+}
+
+/// Items in JSON or YAML, where times are RFC 3339 strings,
+/// with other keys as `R`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ItemsInText<R> {
+    items: Vec<ItemInText>,
+    #[serde(flatten)]
+    rest: R,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct ItemInText {
+    #[serde(rename = "q")]
+    question: String,
+    #[serde(rename = "a")]
+    answer: String,
+
+    #[serde(
+        rename = "first-remember-time",
+        skip_serializing_if = "Option::is_none"
+    )]
+    first_remember_time: Option<UtcTime>,
+    #[serde(rename = "last-check-time", skip_serializing_if = "Option::is_none")]
+    last_check_time: Option<UtcTime>,
+    #[serde(rename = "due-time", skip_serializing_if = "Option::is_none")]
+    due_time: Option<UtcTime>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tag: Option<String>,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    reviews: u32,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    lapses: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<String>,
+    #[serde(rename = "buried-until", skip_serializing_if = "Option::is_none")]
+    buried_until: Option<UtcTime>,
+}
+
+fn is_zero(x: &u32) -> bool {
+    *x == 0
+}
+
+/// the name of the status, or nothing if active.
+fn status_name(status: &Status) -> Option<String> {
+    match status {
+        Status::Active => None,
+        x => Some(x.name().to_string()),
+    }
+}
+
+impl From<ItemInText> for Item {
+    fn from(value: ItemInText) -> Self {
+        Self {
+            question: value.question,
+            answer: value.answer,
+            first_remember_time: value.first_remember_time,
+            last_check_time: value.last_check_time,
+            due_time: value.due_time,
+            tag: value.tag,
+            reviews: value.reviews,
+            lapses: value.lapses,
+            status: Status::from_parts(value.status.as_deref(), value.buried_until),
+        }
+    }
+}
+
+impl From<&Item> for ItemInText {
+    fn from(value: &Item) -> Self {
+        Self {
+            question: value.question.clone(),
+            answer: value.answer.clone(),
+            first_remember_time: value.first_remember_time,
+            last_check_time: value.last_check_time,
+            due_time: value.due_time,
+            tag: value.tag.clone(),
+            reviews: value.reviews,
+            lapses: value.lapses,
+            status: status_name(&value.status),
+            buried_until: value.status.buried_until(),
+        }
+    }
 }
 
 impl From<ItemInDisk> for Item {
@@ -77,6 +349,12 @@ impl From<ItemInDisk> for Item {
                 wrapped.0
             }),
             tag: value.tag,
+            reviews: value.reviews,
+            lapses: value.lapses,
+            status: Status::from_parts(
+                value.status.as_deref(),
+                value.buried_until.map(|x| WrapDatetime::from(x).0),
+            ),
         }
     }
 }
@@ -90,16 +368,24 @@ impl From<&Item> for ItemInDisk {
             last_check_time: value.last_check_time.map(|x| WrapDatetime(x).into()),
             due_time: value.due_time.map(|x| WrapDatetime(x).into()),
             tag: value.tag.clone(),
+            reviews: value.reviews,
+            lapses: value.lapses,
+            status: status_name(&value.status),
+            buried_until: value.status.buried_until().map(|x| WrapDatetime(x).into()),
         }
     }
 }
 
-struct WrapDatetime(UtcTime);
+/// A datetime in UTC, read from any offset and written in UTC.
+pub(crate) struct WrapDatetime(pub(crate) UtcTime);
 
 impl From<toml::value::Datetime> for WrapDatetime {
     fn from(value: toml::value::Datetime) -> Self {
-        assert!(value.offset.is_some());
-        assert_eq!(value.offset.unwrap(), toml::value::Offset::Z);
+        let offset = match value.offset {
+            Some(toml::value::Offset::Z) => 0,
+            Some(toml::value::Offset::Custom { minutes }) => minutes as i32,
+            None => panic!("no offset in datetime: {value}"),
+        };
         let toml_date = value.date.unwrap();
         let toml_time = value.time.unwrap();
         let date = chrono::NaiveDate::from_ymd_opt(
@@ -116,8 +402,9 @@ impl From<toml::value::Datetime> for WrapDatetime {
         )
         .unwrap();
         let datetime = chrono::NaiveDateTime::new(date, time);
-        let datetime = UtcTime::from_naive_utc_and_offset(datetime, Utc);
-        Self(datetime)
+        let offset = chrono::FixedOffset::east_opt(offset * 60).unwrap();
+        let datetime = datetime.and_local_timezone(offset).unwrap();
+        Self(datetime.to_utc())
     }
 }
 
@@ -141,3 +428,39 @@ impl From<WrapDatetime> for toml::value::Datetime {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tell_palaces() {
+        assert!(FileFormat::Toml.has_items("title = \"x\"\n\n[[items]]\nq = \"Q?\"\n"));
+        assert!(FileFormat::Toml.has_items("items = []\n"));
+        assert!(!FileFormat::Toml.has_items("[package]\nname = \"x\"\nitems = 3\n"));
+        assert!(FileFormat::Json.has_items("{\"items\": []}"));
+        assert!(!FileFormat::Json.has_items("{\"name\": \"x\"}"));
+        assert!(FileFormat::Yaml.has_items("items:\n- q: Q?\n"));
+        assert!(!FileFormat::Yaml.has_items("name: x\n"));
+    }
+
+    #[test]
+    fn keep_other_keys() {
+        let old = "title = \"palace\"\n\n[[items]]\nq = \"Q1?\"\na = \"A1.\"\n";
+        let items = FileFormat::Toml.parse(old).unwrap();
+        let mut more = items.clone();
+        more.push(Item {
+            question: "Q2?".to_string(),
+            ..items[0].clone()
+        });
+        let new = FileFormat::Toml.format(&more, Some(old));
+        assert!(new.starts_with("title = \"palace\"\n"));
+        assert_eq!(FileFormat::Toml.parse(&new).unwrap(), more);
+
+        let old = "{\"meta\": {\"x\": 1}, \"items\": [{\"q\": \"Q1?\", \"a\": \"A1.\"}]}";
+        let new = FileFormat::Json.format(&more, Some(old));
+        let value: serde_json::Value = serde_json::from_str(&new).unwrap();
+        assert_eq!(value["meta"]["x"], 1);
+        assert_eq!(FileFormat::Json.parse(&new).unwrap(), more);
+    }
+}
@@ -0,0 +1,57 @@
+use crate::*;
+use log::*;
+use std::collections::HashSet;
+
+/// Conditions which items must meet to be picked up by `select` or `exam`.
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+    /// Selects only items with one of the specified tags
+    pub tags: Option<HashSet<String>>,
+
+    /// Selects only items whose question or answer contains the text, case-insensitively
+    pub query: Option<String>,
+
+    /// Selects only items which have been due for at least the duration
+    pub overdue: Option<chrono::Duration>,
+
+    /// Selects only items forgotten at least so many times, i.e., leeches
+    pub lapses: Option<u32>,
+}
+
+impl Filter {
+    pub fn matches(&self, item: &Item, now: &UtcTime) -> bool {
+        if let Some(overdue) = &self.overdue
+            && let Some(due) = item.due_time
+            && due + *overdue >= *now
+        {
+            debug!("exclude item because of not timing out: {}", item.question);
+            return false;
+        }
+        if let Some(lapses) = self.lapses
+            && item.lapses < lapses
+        {
+            debug!("exclude item because of not a leech: {}", item.question);
+            return false;
+        }
+        if let Some(tags) = &self.tags {
+            let Some(item_tag) = &item.tag else {
+                debug!("exclude item because of no tags: {}", item.question);
+                return false;
+            };
+            if !item_tag.split(" ").any(|x| tags.contains(x)) {
+                debug!("exclude item because of no tags: {}", item.question);
+                return false;
+            }
+        }
+        if let Some(query) = &self.query {
+            let query = query.to_lowercase();
+            if !item.question.to_lowercase().contains(&query)
+                && !item.answer.to_lowercase().contains(&query)
+            {
+                debug!("exclude item because of no match: {}", item.question);
+                return false;
+            }
+        }
+        true
+    }
+}
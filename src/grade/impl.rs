@@ -0,0 +1,83 @@
+use crate::*;
+use chrono::prelude::*;
+use log::*;
+use std::path::PathBuf;
+
+/// Enters grades of a printed sheet.
+pub struct Grade {
+    /// the file, directory or workspace manifest which the sheet is printed from
+    pub input: PathBuf,
+
+    /// the ID of the sheet
+    pub sheet: String,
+
+    /// numbers of wrong items on the sheet, from 1.
+    /// Others are correct.
+    pub wrong: Vec<usize>,
+
+    /// Do everything except writing back.
+    pub dry_run: bool,
+}
+
+impl Grade {
+    pub fn gogogo(&self) {
+        let mut sheet = Sheet::read(&self.input, &self.sheet);
+        if let Some(t) = sheet.grade_time {
+            error!("sheet {} has been graded at {}.", sheet.id, t);
+            return;
+        }
+        if let Some(n) = self
+            .wrong
+            .iter()
+            .find(|n| **n == 0 || **n > sheet.items.len())
+        {
+            error!(
+                "no item {} on sheet {} of {} items.",
+                n,
+                sheet.id,
+                sheet.items.len()
+            );
+            return;
+        }
+
+        let workspace = Workspace::open(std::slice::from_ref(&self.input));
+        let mut decks: Vec<_> = workspace.files.iter().map(|x| x.deck()).collect();
+        let files: Vec<_> = workspace
+            .files
+            .iter()
+            .map(|x| std::fs::canonicalize(&x.path).unwrap())
+            .collect();
+        let now = Utc::now();
+        // Items printed with a check are checked at the time of printing.
+        let check_time = sheet.check_time.unwrap_or(now);
+        let mut graded = vec![vec![]; files.len()];
+        for (i, x) in sheet.items.iter().enumerate() {
+            let Some(f) = files.iter().position(|f| *f == x.file) else {
+                warn!("No file for item {}: {}", i + 1, x.file.display());
+                continue;
+            };
+            let rating = if self.wrong.contains(&(i + 1)) {
+                Rating::Wrong
+            } else {
+                Rating::Correct
+            };
+            let Some(item) = decks[f].review(&x.question, rating, &check_time) else {
+                warn!("No item {}: {}", i + 1, x.question);
+                continue;
+            };
+            graded[f].push(item);
+        }
+
+        if self.dry_run {
+            info!("dry run!");
+            return;
+        }
+        for (f, items) in workspace.files.iter().zip(graded.iter()) {
+            if !items.is_empty() {
+                f.update(items);
+            }
+        }
+        sheet.grade_time = Some(now);
+        sheet.write(&self.input);
+    }
+}
@@ -0,0 +1,3 @@
+mod r#impl;
+
+pub use self::r#impl::*;
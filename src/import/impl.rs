@@ -0,0 +1,55 @@
+use crate::*;
+use log::*;
+use std::{collections::HashSet, path::PathBuf};
+
+pub struct Import {
+    /// the file to import from
+    pub input: PathBuf,
+
+    /// the memory-palace file to import into
+    pub into: PathBuf,
+
+    pub format: ImportFormat,
+
+    /// Merges items of existing questions, instead of skipping them.
+    pub merge: bool,
+}
+
+#[derive(Debug)]
+pub enum ImportFormat {
+    Csv(TableOptions),
+    Anki {
+        /// Keeps HTML of fields as it is, instead of turning it into plain text.
+        keep_html: bool,
+    },
+}
+
+impl Import {
+    pub fn gogogo(&self) {
+        let mut imported = match &self.format {
+            ImportFormat::Csv(opts) => read_table(&self.input, opts),
+            ImportFormat::Anki { keep_html } => read_anki(&self.input, *keep_html),
+        };
+        let store = open_store(&self.into);
+        let mut skipped = 0;
+        if !self.merge && self.into.exists() {
+            let existing: HashSet<_> = store.load().into_iter().map(|x| x.question).collect();
+            imported.retain(|x| {
+                let keep = !existing.contains(&x.question);
+                if !keep {
+                    debug!("skip existing question: {}", x.question);
+                    skipped += 1;
+                }
+                keep
+            });
+        }
+        let appended = store.append(imported);
+        info!(
+            "{} items added, {} skipped and {} updated into {}.",
+            appended.added,
+            appended.skipped + skipped,
+            appended.updated,
+            self.into.display()
+        );
+    }
+}
@@ -1,8 +1,8 @@
-use crate::UtcTime;
+use crate::{DayBoundary, UtcTime};
 use log::*;
 use rand::prelude::*;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Item {
     pub question: String,
     pub answer: String,
@@ -10,37 +10,199 @@ pub struct Item {
     pub last_check_time: Option<UtcTime>,
     pub due_time: Option<UtcTime>,
     pub tag: Option<String>,
+    /// how many times it is checked
+    pub reviews: u32,
+    /// how many times it is forgotten after remembered
+    pub lapses: u32,
+    pub status: Status,
+}
+
+/// Whether an item is taken in reviews.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Status {
+    #[default]
+    Active,
+    /// out of reviews until activated again
+    Suspended,
+    /// out of reviews until the time
+    Buried(UtcTime),
+    /// out of reviews for good, but kept in the file
+    Archived,
 }
 
 const MAX_DURATION: chrono::Duration = chrono::Duration::days(360);
 const INIT_DURATION: chrono::Duration = chrono::Duration::hours(20);
+const LEECH_LAPSES: u32 = 8;
+
+/// the shortest delay of schedulers, so a random timeout can be taken.
+pub const MIN_DURATION: chrono::Duration = chrono::Duration::seconds(2);
+
+/// the tag of leeches, i.e., items forgotten again and again.
+pub const LEECH_TAG: &str = "leech";
+
+/// Settings on how long to wait before checking an item again.
+#[derive(Debug, Clone)]
+pub struct Scheduler {
+    /// the delay after an item is remembered at the first time or is forgotten.
+    pub init_duration: chrono::Duration,
+
+    /// the longest delay between two checks.
+    pub max_duration: chrono::Duration,
+
+    /// Items waiting for a day or more are due when their day begins.
+    pub boundary: DayBoundary,
+
+    /// Items forgotten so many times are leeches, and are tagged so.
+    pub leech_lapses: u32,
+}
+
+impl Default for Scheduler {
+    fn default() -> Self {
+        Self {
+            init_duration: INIT_DURATION,
+            max_duration: MAX_DURATION,
+            boundary: DayBoundary::default(),
+            leech_lapses: LEECH_LAPSES,
+        }
+    }
+}
 
 impl Item {
+    /// A new item, never checked.
+    pub fn new(question: &str, answer: &str) -> Self {
+        Self {
+            question: question.to_string(),
+            answer: answer.to_string(),
+            first_remember_time: None,
+            last_check_time: None,
+            due_time: None,
+            tag: None,
+            reviews: 0,
+            lapses: 0,
+            status: Status::Active,
+        }
+    }
+
     pub fn correct(&mut self, now: &UtcTime) {
+        self.correct_with(now, &Scheduler::default());
+    }
+
+    pub fn wrong(&mut self, now: &UtcTime) {
+        self.wrong_with(now, &Scheduler::default());
+    }
+
+    pub fn correct_with(&mut self, now: &UtcTime, scheduler: &Scheduler) {
         info!("correct: {}", self.question);
         if let Some(ref first_remember_time) = self.first_remember_time {
-            let timeout = timeout(&(*now - *first_remember_time));
-            self.due_time.replace(*now + timeout);
+            let timeout = scheduler.timeout(&(*now - *first_remember_time));
+            self.due_time.replace(scheduler.due(now, timeout));
         } else {
             self.first_remember_time = Some(*now);
-            let timeout = timeout(&INIT_DURATION);
-            self.due_time.replace(*now + timeout);
+            let timeout = scheduler.timeout(&scheduler.init_duration);
+            self.due_time.replace(scheduler.due(now, timeout));
         }
         self.last_check_time.replace(*now);
+        self.reviews += 1;
     }
 
-    pub fn wrong(&mut self, now: &UtcTime) {
+    pub fn wrong_with(&mut self, now: &UtcTime, scheduler: &Scheduler) {
         warn!("  wrong: {}", self.question);
-        self.first_remember_time = None;
-        let timeout = timeout(&INIT_DURATION);
-        self.due_time.replace(*now + timeout);
+        if self.first_remember_time.take().is_some() {
+            self.lapses += 1;
+            if self.is_leech(scheduler) && !self.has_tag(LEECH_TAG) {
+                warn!("  leech: {}", self.question);
+                self.tag = Some(match self.tag.take() {
+                    Some(tag) => format!("{tag} {LEECH_TAG}"),
+                    None => LEECH_TAG.to_string(),
+                });
+            }
+        }
+        let timeout = scheduler.timeout(&scheduler.init_duration);
+        self.due_time.replace(scheduler.due(now, timeout));
         self.last_check_time.replace(*now);
+        self.reviews += 1;
+    }
+
+    /// Whether it is forgotten too many times to be remembered as it is.
+    pub fn is_leech(&self, scheduler: &Scheduler) -> bool {
+        self.lapses >= scheduler.leech_lapses
+    }
+
+    /// Whether it is to be reviewed now: new, or due before now,
+    /// unless out of reviews.
+    pub fn is_due(&self, now: &UtcTime) -> bool {
+        self.due_time.is_none_or(|due| due < *now) && self.is_active(now)
+    }
+
+    /// Whether it is taken in reviews at `now`.
+    pub fn is_active(&self, now: &UtcTime) -> bool {
+        match self.status {
+            Status::Active => true,
+            Status::Buried(until) => until <= *now,
+            Status::Suspended | Status::Archived => false,
+        }
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tag
+            .as_ref()
+            .is_some_and(|x| x.split(' ').any(|x| x == tag))
     }
 }
 
-fn timeout(delay: &chrono::Duration) -> chrono::Duration {
-    let int_dur = delay.min(&MAX_DURATION).num_seconds();
-    let mut rng = rand::rng();
-    let timeout = rng.random_range(int_dur..(int_dur + (int_dur / 2)));
-    chrono::Duration::seconds(timeout)
+impl Status {
+    pub const NAMES: [&str; 4] = ["active", "suspended", "buried", "archived"];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Status::Active => "active",
+            Status::Suspended => "suspended",
+            Status::Buried(_) => "buried",
+            Status::Archived => "archived",
+        }
+    }
+
+    /// the time until which it is buried.
+    pub fn buried_until(&self) -> Option<UtcTime> {
+        match self {
+            Status::Buried(until) => Some(*until),
+            _ => None,
+        }
+    }
+
+    /// Reads the status of its name, and the time until buried.
+    pub fn from_parts(name: Option<&str>, buried_until: Option<UtcTime>) -> Status {
+        match (name, buried_until) {
+            (None | Some("active"), _) => Status::Active,
+            (Some("suspended"), _) => Status::Suspended,
+            (Some("buried"), Some(until)) => Status::Buried(until),
+            (Some("buried"), None) => panic!("no buried-until of a buried item"),
+            (Some("archived"), _) => Status::Archived,
+            (Some(x), _) => panic!("unknown status: {x}"),
+        }
+    }
+}
+
+impl Scheduler {
+    fn timeout(&self, delay: &chrono::Duration) -> chrono::Duration {
+        // Items checked again right after remembered wait at least as long.
+        let int_dur = delay
+            .min(&self.max_duration)
+            .max(&MIN_DURATION)
+            .num_seconds();
+        let mut rng = rand::rng();
+        let timeout = rng.random_range(int_dur..(int_dur + (int_dur / 2)));
+        chrono::Duration::seconds(timeout)
+    }
+
+    /// the due time after waiting `timeout`,
+    /// which is at the beginning of a day after today if waiting for a day or more.
+    fn due(&self, now: &UtcTime, timeout: chrono::Duration) -> UtcTime {
+        let due = *now + timeout;
+        if timeout >= chrono::Duration::days(1) {
+            self.boundary.floor_after(&due, now)
+        } else {
+            due
+        }
+    }
 }
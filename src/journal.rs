@@ -0,0 +1,174 @@
+use crate::{
+    file::{ItemInText, WrapDatetime},
+    *,
+};
+use log::*;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// the hidden directory of session journals, beside the daily state.
+const SESSIONS_DIR: &str = ".sessions";
+
+/// A journal of an exam session: the items it changed, before and after.
+///
+/// It is kept beside the workspace, so the session can be undone afterwards.
+/// Times of items are kept as RFC 3339 strings, in full precision.
+#[derive(Debug, Clone)]
+pub struct Session {
+    pub id: String,
+    pub time: UtcTime,
+    pub undo_time: Option<UtcTime>,
+    pub items: Vec<SessionItem>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SessionItem {
+    /// the palace file where the item is
+    pub file: PathBuf,
+    pub before: Item,
+    pub after: Item,
+}
+
+impl Session {
+    /// A session of a workspace at `time`, identified by the time.
+    ///
+    /// A suffix like `-2` tells sessions in the same second apart,
+    /// so journals of earlier ones are never overwritten.
+    pub fn new<S: Store>(workspace: &Workspace<S>, time: UtcTime, items: Vec<SessionItem>) -> Self {
+        let base = time.format("%Y%m%dT%H%M%S").to_string();
+        let mut id = base.clone();
+        for n in 2.. {
+            if !session_path(workspace, &id).exists() {
+                break;
+            }
+            id = format!("{base}-{n}");
+        }
+        Self {
+            id,
+            time,
+            undo_time: None,
+            items,
+        }
+    }
+
+    /// IDs of sessions of a workspace, the earliest first.
+    pub fn list<S: Store>(workspace: &Workspace<S>) -> Vec<String> {
+        let Some(dir) = sessions_dir(workspace) else {
+            return vec![];
+        };
+        if !dir.is_dir() {
+            return vec![];
+        }
+        let mut res: Vec<_> = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|x| x.unwrap().path())
+            .filter(|x| x.extension().is_some_and(|x| x == "toml"))
+            .map(|x| x.file_stem().unwrap().to_string_lossy().into_owned())
+            .collect();
+        res.sort();
+        res
+    }
+
+    /// Reads the journal of a session of a workspace.
+    pub fn read<S: Store>(workspace: &Workspace<S>, id: &str) -> Self {
+        let file_name = session_path(workspace, id);
+        debug!("read session {}", file_name.display());
+        let content = std::fs::read_to_string(&file_name).unwrap();
+        let session: SessionInDisk = toml::from_str(&content).unwrap();
+        Self {
+            id: id.to_string(),
+            time: WrapDatetime::from(session.time).0,
+            undo_time: session.undo_time.map(|x| WrapDatetime::from(x).0),
+            items: session
+                .items
+                .into_iter()
+                .map(|x| SessionItem {
+                    file: x.file,
+                    before: x.before.into(),
+                    after: x.after.into(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Writes the journal of a session of a workspace.
+    pub fn write<S: Store>(&self, workspace: &Workspace<S>) {
+        let file_name = session_path(workspace, &self.id);
+        debug!("write session {}", file_name.display());
+        std::fs::create_dir_all(file_name.parent().unwrap()).unwrap();
+        let session = SessionInDisk {
+            time: WrapDatetime(self.time).into(),
+            undo_time: self.undo_time.map(|x| WrapDatetime(x).into()),
+            items: self
+                .items
+                .iter()
+                .map(|x| SessionItemInDisk {
+                    file: x.file.clone(),
+                    before: (&x.before).into(),
+                    after: (&x.after).into(),
+                })
+                .collect(),
+        };
+        let content = toml::to_string_pretty(&session).unwrap();
+        std::fs::write(file_name, content).unwrap();
+    }
+}
+
+/// the directory of journals, which is beside the daily state of the workspace.
+fn sessions_dir<S: Store>(workspace: &Workspace<S>) -> Option<PathBuf> {
+    let state = workspace.state.as_ref()?;
+    let dir = match state.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    Some(dir.join(SESSIONS_DIR))
+}
+
+fn session_path<S: Store>(workspace: &Workspace<S>, id: &str) -> PathBuf {
+    parse_sheet_id(id).unwrap();
+    sessions_dir(workspace)
+        .expect("no journals of sessions in a workspace of stores")
+        .join(format!("{}.toml", id))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SessionInDisk {
+    time: toml::value::Datetime,
+    #[serde(rename = "undo-time")]
+    undo_time: Option<toml::value::Datetime>,
+    items: Vec<SessionItemInDisk>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SessionItemInDisk {
+    file: PathBuf,
+    before: ItemInText,
+    after: ItemInText,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::prelude::*;
+
+    #[test]
+    fn sessions_in_the_same_second() {
+        let dir =
+            std::env::temp_dir().join(format!("memory-palace-journal-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("a.toml");
+        write_out(&path, &[Item::new("Q?", "A.")]);
+        let workspace = Workspace::open(std::slice::from_ref(&path));
+
+        let now = Utc.with_ymd_and_hms(2024, 1, 2, 3, 4, 5).unwrap();
+        let first = Session::new(&workspace, now, vec![]);
+        first.write(&workspace);
+        let second = Session::new(&workspace, now, vec![]);
+        second.write(&workspace);
+        assert_eq!(first.id, "20240102T030405");
+        assert_eq!(second.id, "20240102T030405-2");
+        assert_eq!(Session::list(&workspace), vec![first.id, second.id]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,11 +1,45 @@
+mod anki;
+pub mod convert;
+mod daily;
+mod deck;
 pub mod exam;
+pub mod export;
 mod file;
+mod filter;
+pub mod grade;
+pub mod import;
 mod item;
+mod journal;
+mod merge;
 pub mod print;
+mod sample;
 pub mod select;
+mod sheet;
+mod sqlite;
+pub mod stats;
+pub mod status;
+mod store;
+pub mod sync;
+mod table;
+pub mod undo;
 pub mod update;
+mod workspace;
 
+pub use self::anki::*;
+pub use self::daily::*;
+pub use self::deck::*;
 pub use self::file::*;
+pub use self::filter::*;
 pub use self::item::*;
+pub use self::journal::*;
+pub use self::sample::*;
+pub use self::sheet::*;
+pub use self::sqlite::*;
+pub use self::store::*;
+pub use self::table::*;
+pub use self::workspace::*;
 
 type UtcTime = chrono::DateTime<chrono::Utc>;
+
+/// the font of CJK characters, for both the GUI and printing.
+static CJK_FONT: &[u8] = include_bytes!("/usr/share/fonts/opentype/noto/NotoSansCJK-Regular.ttc");
@@ -1,26 +1,64 @@
-use clap::{Arg, ArgAction, Command, crate_name, crate_version, value_parser};
+use clap::{Arg, ArgAction, ArgGroup, Command, crate_name, crate_version, value_parser};
 use clap_complete::aot as completion;
-use memory_palace::{exam::Exam, print::Print, select::Select, update::Update};
+use memory_palace::{
+    Column, Filter, Quota, SampleOrder, Sampling, Scheduler, Status, TableOptions, Workspace,
+    convert::Convert,
+    exam::Exam,
+    export::{Export, ExportFormat},
+    grade::Grade,
+    import::{Import, ImportFormat},
+    open_store, parse_duration, parse_quota, parse_sheet_id,
+    print::{CardLayout, Format, Print, Selection},
+    select::Select,
+    stats::Stats,
+    status::SetStatus,
+    sync::Synchronize,
+    undo::Undo,
+    update::Update,
+};
 use std::{collections::HashSet, path::PathBuf};
 
 fn main() {
     flexi_logger::Logger::try_with_env_or_str("error, memory_palace=info")
         .unwrap()
-        .use_utc()
         .adaptive_format_for_stderr(flexi_logger::AdaptiveFormat::Detailed)
         .start()
         .unwrap();
     let args = parse_args();
     match args {
+        Args::Convert(convert) => {
+            convert.gogogo();
+        }
         Args::Exam(exam) => {
             exam.gogogo();
         }
+        Args::Export(export) => {
+            export.gogogo();
+        }
+        Args::Import(import) => {
+            import.gogogo();
+        }
+        Args::Grade(grade) => {
+            grade.gogogo();
+        }
         Args::Select(select) => {
             select.gogogo();
         }
         Args::Print(print) => {
             print.gogogo();
         }
+        Args::Stats(stats) => {
+            stats.gogogo();
+        }
+        Args::SetStatus(set_status) => {
+            set_status.gogogo();
+        }
+        Args::Sync(sync) => {
+            sync.gogogo();
+        }
+        Args::Undo(undo) => {
+            undo.gogogo();
+        }
         Args::Update(update) => {
             update.gogogo();
         }
@@ -30,11 +68,22 @@ fn main() {
 fn parse_args() -> Args {
     const COMPLETION: &str = "completion";
     const COMPLETION_SHELL: &str = "completion/SHELL";
+    const CONVERT: &str = "convert";
+    const CONVERT_INPUT: &str = "convert/INPUT";
+    const CONVERT_OUTPUT: &str = "convert/OUTPUT";
     const EXAM: &str = "exam";
     const EXAM_FILE_NAME: &str = "exam/FILE_NAME";
     const EXAM_TAKE: &str = "exam/TAKE";
+    const EXAM_TAKE_PER_FILE: &str = "exam/TAKE-PER-FILE";
     const EXAM_DRY_RUN: &str = "exam/DRY-RUN";
     const EXAM_SORT: &str = "exam/SORT";
+    const EXAM_TAGS: &str = "exam/TAGS";
+    const EXAM_QUERY: &str = "exam/QUERY";
+    const EXAM_OVERDUE: &str = "exam/OVERDUE";
+    const EXAM_NEW_PER_DAY: &str = "exam/NEW-PER-DAY";
+    const EXAM_REVIEWS_PER_DAY: &str = "exam/REVIEWS-PER-DAY";
+    const SAMPLE_ORDER: &str = "sample/ORDER";
+    const SAMPLE_QUOTAS: &str = "sample/QUOTAS";
     const SELECT: &str = "select";
     const SELECT_IN: &str = "select/IN-FILE";
     const SELECT_OUT: &str = "select/OUT-FILE";
@@ -42,26 +91,361 @@ fn parse_args() -> Args {
     const SELECT_TAKE: &str = "select/TAKE";
     const SELECT_TAGS: &str = "select/TAGS";
     const SELECT_SORT: &str = "select/SORT";
+    const SELECT_QUERY: &str = "select/QUERY";
+    const SELECT_OVERDUE: &str = "select/OVERDUE";
+    const SELECT_MOVE: &str = "select/MOVE";
+    const SELECT_LEECH: &str = "select/LEECH";
+    const SELECT_DRY_RUN: &str = "select/DRY-RUN";
     const PRINT: &str = "print";
     const PRINT_TYPST: &str = "typst";
-    const PRINT_TYPST_INPUT: &str = "print/typst/INPUT";
-    const PRINT_TYPST_OUTPUT: &str = "print/typst/OUTPUT";
+    const PRINT_PDF: &str = "pdf";
+    const PRINT_MARKDOWN: &str = "markdown";
+    const PRINT_HTML: &str = "html";
+    const PRINT_CARDS: &str = "cards";
+    const PRINT_INPUT: &str = "print/INPUT";
+    const PRINT_OUTPUT: &str = "print/OUTPUT";
+    const PRINT_TEMPLATE: &str = "print/TEMPLATE";
+    const PRINT_TAGS: &str = "print/TAGS";
+    const PRINT_QUERY: &str = "print/QUERY";
+    const PRINT_TAKE: &str = "print/TAKE";
+    const PRINT_SHUFFLE: &str = "print/SHUFFLE";
+    const PRINT_SEED: &str = "print/SEED";
+    const PRINT_QUESTIONS_ONLY: &str = "print/QUESTIONS-ONLY";
+    const PRINT_ANSWER_KEY: &str = "print/ANSWER-KEY";
+    const PRINT_CHECK: &str = "print/CHECK";
+    const PRINT_SHEET: &str = "print/SHEET";
+    const PRINT_FORCE: &str = "print/FORCE";
+    const GRADE: &str = "grade";
+    const GRADE_INPUT: &str = "grade/INPUT";
+    const GRADE_SHEET: &str = "grade/SHEET";
+    const GRADE_WRONG: &str = "grade/WRONG";
+    const GRADE_DRY_RUN: &str = "grade/DRY-RUN";
+    const PRINT_COLLAPSE: &str = "print/COLLAPSE";
+    const PRINT_COLUMNS: &str = "print/COLUMNS";
+    const PRINT_ROWS: &str = "print/ROWS";
+    const PRINT_CARD_WIDTH: &str = "print/CARD-WIDTH";
+    const PRINT_CARD_HEIGHT: &str = "print/CARD-HEIGHT";
+    const IMPORT: &str = "import";
+    const IMPORT_INPUT: &str = "import/INPUT";
+    const IMPORT_INTO: &str = "import/INTO";
+    const IMPORT_MERGE: &str = "import/MERGE";
+    const EXPORT: &str = "export";
+    const EXPORT_INPUT: &str = "export/INPUT";
+    const EXPORT_OUTPUT: &str = "export/OUTPUT";
+    const TABLE_CSV: &str = "csv";
+    const TABLE_TSV: &str = "table/TSV";
+    const TABLE_COLUMNS: &str = "table/COLUMNS";
+    const TABLE_NO_HEADER: &str = "table/NO-HEADER";
+    const TABLE_BOM: &str = "table/BOM";
+    const ANKI: &str = "anki";
+    const ANKI_KEEP_HTML: &str = "anki/KEEP-HTML";
+    const ANKI_DECK: &str = "anki/DECK";
+    const STATS: &str = "stats";
+    const STATS_INPUTS: &str = "stats/INPUTS";
+    const SUSPEND: &str = "suspend";
+    const BURY: &str = "bury";
+    const BURY_FOR: &str = "bury/FOR";
+    const ARCHIVE: &str = "archive";
+    const ACTIVATE: &str = "activate";
+    const STATUS_INPUTS: &str = "status/INPUTS";
+    const STATUS_IDS: &str = "status/IDS";
+    const STATUS_TAGS: &str = "status/TAGS";
+    const STATUS_QUERY: &str = "status/QUERY";
+    const STATUS_DRY_RUN: &str = "status/DRY-RUN";
+    const SYNC: &str = "sync";
+    const SYNC_LOCALS: &str = "sync/LOCALS";
+    const SYNC_SHARED: &str = "sync/SHARED";
+    const UNDO: &str = "undo";
+    const UNDO_INPUTS: &str = "undo/INPUTS";
+    const UNDO_SESSION: &str = "undo/SESSION";
+    const UNDO_LIST: &str = "undo/LIST";
+    const UNDO_DRY_RUN: &str = "undo/DRY-RUN";
     const UPDATE: &str = "update";
     const UPDATE_INTO: &str = "update/INTO";
     const UPDATE_FROM: &str = "update/FROM";
+    const UPDATE_BASE: &str = "update/BASE";
+    const UPDATE_DRY_RUN: &str = "update/DRY-RUN";
+    const UPDATE_REMOVE_MERGED: &str = "update/REMOVE-MERGED";
+
+    let sampling_args = |cmd: Command| {
+        cmd.arg(
+            Arg::new(SAMPLE_ORDER)
+                .value_name("ORDER")
+                .help("takes items at random, the most overdue first, new ones first, or at random weighted by overdue days.")
+                .long("sample")
+                .default_value("random")
+                .value_parser(SampleOrder::NAMES)
+                .action(ArgAction::Set),
+        )
+        .arg(
+            Arg::new(SAMPLE_QUOTAS)
+                .value_name("TAG=N")
+                .help("takes at most <N> items, or <N>% of taken items, of <TAG>, e.g., math=10 or math=30%.")
+                .long("quota")
+                .num_args(1..)
+                .action(ArgAction::Append)
+                .value_parser(parse_quota),
+        )
+    };
+    let sampling = |matches: &clap::ArgMatches| Sampling {
+        order: SampleOrder::parse(matches.get_one::<String>(SAMPLE_ORDER).unwrap()).unwrap(),
+        quotas: matches
+            .get_many::<(String, Quota)>(SAMPLE_QUOTAS)
+            .map(|xs| xs.cloned().collect())
+            .unwrap_or_default(),
+    };
+
+    let status_command = |name: &'static str, about: &'static str| {
+        Command::new(name)
+            .about(about)
+            .arg(
+                Arg::new(STATUS_INPUTS)
+                    .value_name("INPUT")
+                    .help("the files, directories or workspace manifests of memory palaces.")
+                    .required(true)
+                    .action(ArgAction::Set)
+                    .num_args(1..)
+                    .value_parser(value_parser!(PathBuf)),
+            )
+            .arg(
+                Arg::new(STATUS_IDS)
+                    .value_name("QUESTION")
+                    .help("changes only the items of the questions, which identify items.")
+                    .long("id")
+                    .num_args(1..)
+                    .action(ArgAction::Append),
+            )
+            .arg(
+                Arg::new(STATUS_TAGS)
+                    .value_name("TAG")
+                    .help("changes only items with one of the specified tags.")
+                    .long("tag")
+                    .num_args(1..)
+                    .action(ArgAction::Append),
+            )
+            .arg(
+                Arg::new(STATUS_QUERY)
+                    .value_name("TEXT")
+                    .help("changes only items whose question or answer contains <TEXT>.")
+                    .long("query")
+                    .action(ArgAction::Set),
+            )
+            .group(
+                ArgGroup::new("status/ITEMS")
+                    .args([STATUS_IDS, STATUS_TAGS, STATUS_QUERY])
+                    .multiple(true)
+                    .required(true),
+            )
+            .arg(
+                Arg::new(STATUS_DRY_RUN)
+                    .help("prints the items to be changed without writing.")
+                    .long("dry-run")
+                    .action(ArgAction::SetTrue),
+            )
+    };
+    let import_command = |name: &'static str, about: &'static str| {
+        Command::new(name)
+            .about(about)
+            .arg(
+                Arg::new(IMPORT_INPUT)
+                    .value_name("INPUT")
+                    .help("the file to import from.")
+                    .required(true)
+                    .action(ArgAction::Set)
+                    .value_parser(value_parser!(PathBuf)),
+            )
+            .arg(
+                Arg::new(IMPORT_INTO)
+                    .value_name("INTO")
+                    .help("the memory-palace file to import into. It is created if absent.")
+                    .required(true)
+                    .action(ArgAction::Set)
+                    .value_parser(value_parser!(PathBuf)),
+            )
+            .arg(
+                Arg::new(IMPORT_MERGE)
+                    .help("merges items of existing questions instead of skipping them.")
+                    .long("merge")
+                    .action(ArgAction::SetTrue),
+            )
+    };
+    let export_command = |name: &'static str, about: &'static str| {
+        Command::new(name)
+            .about(about)
+            .arg(
+                Arg::new(EXPORT_INPUT)
+                    .value_name("INPUT")
+                    .help("the file, directory or workspace manifest of a memory palace.")
+                    .required(true)
+                    .action(ArgAction::Set)
+                    .value_parser(value_parser!(PathBuf)),
+            )
+            .arg(
+                Arg::new(EXPORT_OUTPUT)
+                    .value_name("OUTPUT")
+                    .help("the file to export into.")
+                    .required(true)
+                    .action(ArgAction::Set)
+                    .value_parser(value_parser!(PathBuf)),
+            )
+    };
+    let table_args = |cmd: Command, columns_help: &'static str| {
+        cmd.arg(
+            Arg::new(TABLE_TSV)
+                .help("uses tabs instead of commas as delimiters.")
+                .long("tsv")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new(TABLE_COLUMNS)
+                .value_name("COLUMN")
+                .help(columns_help)
+                .long("columns")
+                .allow_hyphen_values(true)
+                .value_delimiter(',')
+                .num_args(1)
+                .action(ArgAction::Append)
+                .value_parser(Column::parse),
+        )
+        .arg(
+            Arg::new(TABLE_NO_HEADER)
+                .help("treats the first row as an item rather than the header.")
+                .long("no-header")
+                .action(ArgAction::SetTrue),
+        )
+    };
+    let table_options = |matches: &clap::ArgMatches| TableOptions {
+        delimiter: if matches.get_flag(TABLE_TSV) {
+            b'\t'
+        } else {
+            b','
+        },
+        columns: matches
+            .get_many::<Column>(TABLE_COLUMNS)
+            .map(|xs| xs.copied().collect()),
+        header: !matches.get_flag(TABLE_NO_HEADER),
+        bom: matches
+            .try_get_one::<bool>(TABLE_BOM)
+            .ok()
+            .flatten()
+            .copied()
+            .unwrap_or(false),
+    };
+
+    let print_command = |name: &'static str, about: &'static str, output_help: &'static str| {
+        Command::new(name)
+            .about(about)
+            .arg(
+                Arg::new(PRINT_INPUT)
+                    .value_name("INPUT")
+                    .help("the file, directory or workspace manifest of a memory palace.")
+                    .required(true)
+                    .action(ArgAction::Set)
+                    .value_parser(value_parser!(PathBuf)),
+            )
+            .arg(
+                Arg::new(PRINT_OUTPUT)
+                    .value_name("OUTPUT")
+                    .help(output_help)
+                    .required(true)
+                    .action(ArgAction::Set)
+                    .value_parser(value_parser!(PathBuf)),
+            )
+            .arg(
+                Arg::new(PRINT_TAGS)
+                    .value_name("TAG")
+                    .help("prints only items with one of the specified tags.")
+                    .long("tag")
+                    .num_args(1..)
+                    .action(ArgAction::Append),
+            )
+            .arg(
+                Arg::new(PRINT_QUERY)
+                    .value_name("TEXT")
+                    .help("prints only items whose question or answer contains <TEXT>.")
+                    .long("query")
+                    .action(ArgAction::Set),
+            )
+            .arg(
+                Arg::new(PRINT_TAKE)
+                    .value_name("N")
+                    .help("prints at most <N> items.")
+                    .long("take")
+                    .action(ArgAction::Set)
+                    .value_parser(value_parser!(usize)),
+            )
+            .arg(
+                Arg::new(PRINT_SHUFFLE)
+                    .help("shuffles items before taking.")
+                    .long("shuffle")
+                    .action(ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new(PRINT_SEED)
+                    .value_name("SEED")
+                    .help("shuffles by <SEED>, so the same worksheet can be printed again.")
+                    .long("seed")
+                    .requires(PRINT_SHUFFLE)
+                    .action(ArgAction::Set)
+                    .value_parser(value_parser!(u64)),
+            )
+            .arg(
+                Arg::new(PRINT_QUESTIONS_ONLY)
+                    .help("prints questions only, leaving answers blank.")
+                    .long("questions-only")
+                    .action(ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new(PRINT_ANSWER_KEY)
+                    .value_name("FILE")
+                    .help("prints the answer key into <FILE> as well, in the same numbering.")
+                    .long("answer-key")
+                    .action(ArgAction::Set)
+                    .value_parser(value_parser!(PathBuf)),
+            )
+            .arg(
+                Arg::new(PRINT_CHECK)
+                    .help("records printed items on the sheet as checked now, which takes effect when grades are entered by `grade`.")
+                    .long("check")
+                    .requires(PRINT_SHEET)
+                    .action(ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new(PRINT_SHEET)
+                    .value_name("ID")
+                    .help("records the sheet as <ID>, so grades can be entered by `grade` afterwards.")
+                    .long("sheet")
+                    .action(ArgAction::Set)
+                    .value_parser(parse_sheet_id),
+            )
+            .arg(
+                Arg::new(PRINT_FORCE)
+                    .help("records the sheet even if <ID> exists, replacing it.")
+                    .long("force")
+                    .requires(PRINT_SHEET)
+                    .action(ArgAction::SetTrue),
+            )
+    };
+    let print_template = Arg::new(PRINT_TEMPLATE)
+        .value_name("TEMPLATE")
+        .help("the typst template which prints `items`, `date` and `tags`.")
+        .long("template")
+        .action(ArgAction::Set)
+        .value_parser(value_parser!(PathBuf));
 
     let mut cmd = Command::new(crate_name!())
         .about("Do an exam in the memory palace.")
         .version(crate_version!())
         .subcommand_required(true)
         .subcommand(
-            Command::new(EXAM)
+            sampling_args(Command::new(EXAM))
                 .about("Do an exam.")
                 .arg(
                     Arg::new(EXAM_FILE_NAME)
-                        .help("the file of a memory palace.")
+                        .help("the files, directories or workspace manifests of memory palaces.")
                         .action(ArgAction::Set)
                         .required(true)
+                        .num_args(1..)
                         .value_parser(value_parser!(PathBuf)),
                 )
                 .arg(
@@ -72,6 +456,14 @@ fn parse_args() -> Args {
                         .action(ArgAction::Set)
                         .value_parser(value_parser!(usize)),
                 )
+                .arg(
+                    Arg::new(EXAM_TAKE_PER_FILE)
+                        .value_name("N")
+                        .help("Take at most <N> items from each file.")
+                        .long("take-per-file")
+                        .action(ArgAction::Set)
+                        .value_parser(value_parser!(usize)),
+                )
                 .arg(
                     Arg::new(EXAM_DRY_RUN)
                         .help("Do everything except writing back to disks.")
@@ -83,15 +475,54 @@ fn parse_args() -> Args {
                         .help("Sort items.")
                         .long("sort")
                         .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new(EXAM_TAGS)
+                        .value_name("TAG")
+                        .help("Examine only items with one of the specified tags.")
+                        .long("tag")
+                        .num_args(1..)
+                        .action(ArgAction::Append),
+                )
+                .arg(
+                    Arg::new(EXAM_QUERY)
+                        .value_name("TEXT")
+                        .help("Examine only items whose question or answer contains <TEXT>.")
+                        .long("query")
+                        .action(ArgAction::Set),
+                )
+                .arg(
+                    Arg::new(EXAM_OVERDUE)
+                        .value_name("DURATION")
+                        .help("Examine only items due for at least <DURATION>, e.g., 12h or 3d.")
+                        .long("overdue")
+                        .action(ArgAction::Set)
+                        .value_parser(parse_duration),
+                )
+                .arg(
+                    Arg::new(EXAM_NEW_PER_DAY)
+                        .value_name("N")
+                        .help("Examine at most <N> new items a day, overriding the workspace manifest.")
+                        .long("new-per-day")
+                        .action(ArgAction::Set)
+                        .value_parser(value_parser!(usize)),
+                )
+                .arg(
+                    Arg::new(EXAM_REVIEWS_PER_DAY)
+                        .value_name("N")
+                        .help("Examine at most <N> items checked before a day, overriding the workspace manifest.")
+                        .long("reviews-per-day")
+                        .action(ArgAction::Set)
+                        .value_parser(value_parser!(usize)),
                 ),
         )
         .subcommand(
-            Command::new(SELECT)
+            sampling_args(Command::new(SELECT))
                 .about("Select some items.")
                 .arg(
                     Arg::new(SELECT_IN)
                         .value_name("IN-FILE")
-                        .help("the file of a memory palace.")
+                        .help("the file, directory or workspace manifest of a memory palace.")
                         .required(true)
                         .action(ArgAction::Set)
                         .value_parser(value_parser!(PathBuf)),
@@ -113,7 +544,7 @@ fn parse_args() -> Args {
                 .arg(
                     Arg::new(SELECT_TAKE)
                         .value_name("N")
-                        .help("takes randomly at most <N> items.")
+                        .help("takes at most <N> items.")
                         .long("take")
                         .action(ArgAction::Set)
                         .value_parser(value_parser!(usize)),
@@ -131,6 +562,43 @@ fn parse_args() -> Args {
                         .help("sorts the selected items.")
                         .long("sort")
                         .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new(SELECT_QUERY)
+                        .value_name("TEXT")
+                        .help("selects only items whose question or answer contains <TEXT>.")
+                        .long("query")
+                        .action(ArgAction::Set),
+                )
+                .arg(
+                    Arg::new(SELECT_OVERDUE)
+                        .value_name("DURATION")
+                        .help("selects only items due for at least <DURATION>, e.g., 12h or 3d.")
+                        .long("overdue")
+                        .conflicts_with(SELECT_TIMEOUT)
+                        .action(ArgAction::Set)
+                        .value_parser(parse_duration),
+                )
+                .arg(
+                    Arg::new(SELECT_LEECH)
+                        .value_name("N")
+                        .help("selects only leeches, which are forgotten at least <N> times, as many as `leech-lapses` of the workspace by default.")
+                        .long("leech")
+                        .num_args(0..=1)
+                        .action(ArgAction::Set)
+                        .value_parser(value_parser!(u32)),
+                )
+                .arg(
+                    Arg::new(SELECT_MOVE)
+                        .help("removes the selected items from IN-FILE, moving them to OUT-FILE.")
+                        .long("move")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new(SELECT_DRY_RUN)
+                        .help("prints the selected items without writing.")
+                        .long("dry-run")
+                        .action(ArgAction::SetTrue),
                 ),
         )
         .subcommand(
@@ -138,24 +606,263 @@ fn parse_args() -> Args {
                 .about("Prints a memory palace.")
                 .subcommand_required(true)
                 .subcommand(
-                    Command::new(PRINT_TYPST)
-                        .about("Prints a memory palace in typst format.")
-                        .arg(
-                            Arg::new(PRINT_TYPST_INPUT)
-                                .value_name("INPUT")
-                                .help("the file of a memory palace.")
-                                .required(true)
-                                .action(ArgAction::Set)
-                                .value_parser(value_parser!(PathBuf)),
-                        )
-                        .arg(
-                            Arg::new(PRINT_TYPST_OUTPUT)
-                                .value_name("OUTPUT")
-                                .help("the typst file to be printed.")
-                                .required(true)
-                                .action(ArgAction::Set)
-                                .value_parser(value_parser!(PathBuf)),
-                        ),
+                    print_command(
+                        PRINT_TYPST,
+                        "Prints a memory palace in typst format.",
+                        "the typst file to be printed.",
+                    )
+                    .arg(print_template.clone()),
+                )
+                .subcommand(
+                    print_command(
+                        PRINT_PDF,
+                        "Prints a memory palace into a PDF file.",
+                        "the PDF file to be printed.",
+                    )
+                    .arg(print_template.clone()),
+                )
+                .subcommand(print_command(
+                    PRINT_MARKDOWN,
+                    "Prints a memory palace in a markdown table.",
+                    "the markdown file to be printed.",
+                ))
+                .subcommand(
+                    print_command(
+                        PRINT_HTML,
+                        "Prints a memory palace into a self-contained HTML file.",
+                        "the HTML file to be printed.",
+                    )
+                    .arg(
+                        Arg::new(PRINT_COLLAPSE)
+                            .help("hides answers until they are clicked.")
+                            .long("collapse")
+                            .action(ArgAction::SetTrue),
+                    ),
+                )
+                .subcommand(
+                    print_command(
+                        PRINT_CARDS,
+                        "Prints a memory palace in double-sided cards, questions at front and answers at back.",
+                        "the PDF file if it ends with `.pdf`, or the typst file otherwise.",
+                    )
+                    .arg(
+                        Arg::new(PRINT_COLUMNS)
+                            .value_name("N")
+                            .help("prints <N> columns of cards per page.")
+                            .long("columns")
+                            .default_value("2")
+                            .action(ArgAction::Set)
+                            .value_parser(value_parser!(u64).range(1..)),
+                    )
+                    .arg(
+                        Arg::new(PRINT_ROWS)
+                            .value_name("N")
+                            .help("prints <N> rows of cards per page.")
+                            .long("rows")
+                            .default_value("4")
+                            .action(ArgAction::Set)
+                            .value_parser(value_parser!(u64).range(1..)),
+                    )
+                    .arg(
+                        Arg::new(PRINT_CARD_WIDTH)
+                            .value_name("MM")
+                            .help("the width of cards in millimeters.")
+                            .long("card-width")
+                            .default_value("85")
+                            .action(ArgAction::Set)
+                            .value_parser(value_parser!(f64)),
+                    )
+                    .arg(
+                        Arg::new(PRINT_CARD_HEIGHT)
+                            .value_name("MM")
+                            .help("the height of cards in millimeters.")
+                            .long("card-height")
+                            .default_value("55")
+                            .action(ArgAction::Set)
+                            .value_parser(value_parser!(f64)),
+                    ),
+                ),
+        )
+        .subcommand(
+            Command::new(GRADE)
+                .about("Enters grades of a printed sheet.")
+                .arg(
+                    Arg::new(GRADE_INPUT)
+                        .value_name("INPUT")
+                        .help("the file, directory or workspace manifest which the sheet is printed from.")
+                        .required(true)
+                        .action(ArgAction::Set)
+                        .value_parser(value_parser!(PathBuf)),
+                )
+                .arg(
+                    Arg::new(GRADE_SHEET)
+                        .value_name("ID")
+                        .help("the ID of the sheet.")
+                        .long("sheet")
+                        .required(true)
+                        .action(ArgAction::Set)
+                        .value_parser(parse_sheet_id),
+                )
+                .arg(
+                    Arg::new(GRADE_WRONG)
+                        .value_name("N")
+                        .help("numbers of wrong items on the sheet, e.g., 3,7,12. Others are correct.")
+                        .long("wrong")
+                        .value_delimiter(',')
+                        .num_args(1..)
+                        .action(ArgAction::Append)
+                        .value_parser(value_parser!(usize)),
+                )
+                .arg(
+                    Arg::new(GRADE_DRY_RUN)
+                        .help("Do everything except writing back to disks.")
+                        .long("dry-run")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new(IMPORT)
+                .about("Imports items from other formats into a memory-palace file.")
+                .subcommand_required(true)
+                .subcommand(table_args(
+                    import_command(TABLE_CSV, "Imports items from a CSV or TSV file."),
+                    "columns of the file in order, among q, a, tag, first-remember-time, last-check-time, due-time, or - to skip. Told by the header by default.",
+                ))
+                .subcommand(
+                    import_command(ANKI, "Imports notes from an Anki .apkg or .colpkg file.").arg(
+                        Arg::new(ANKI_KEEP_HTML)
+                            .help("keeps HTML of fields as it is, instead of turning it into plain text.")
+                            .long("keep-html")
+                            .action(ArgAction::SetTrue),
+                    ),
+                ),
+        )
+        .subcommand(
+            Command::new(EXPORT)
+                .about("Exports a memory palace into other formats.")
+                .subcommand_required(true)
+                .subcommand(
+                    table_args(
+                        export_command(TABLE_CSV, "Exports items into a CSV or TSV file."),
+                        "columns to export in order, among q, a, tag, first-remember-time, last-check-time, due-time. All by default.",
+                    )
+                    .arg(
+                        Arg::new(TABLE_BOM)
+                            .help("writes a UTF-8 BOM, which some spreadsheets require.")
+                            .long("bom")
+                            .action(ArgAction::SetTrue),
+                    ),
+                )
+                .subcommand(
+                    export_command(ANKI, "Exports items into an Anki .apkg file.").arg(
+                        Arg::new(ANKI_DECK)
+                            .value_name("NAME")
+                            .help("the deck of exported notes. It is the name of OUTPUT by default.")
+                            .long("deck")
+                            .action(ArgAction::Set),
+                    ),
+                ),
+        )
+        .subcommand(
+            Command::new(CONVERT)
+                .about("Converts a memory-palace file among TOML, JSON, YAML and SQLite, told by extensions.")
+                .arg(
+                    Arg::new(CONVERT_INPUT)
+                        .value_name("INPUT")
+                        .help("the file to convert from.")
+                        .required(true)
+                        .action(ArgAction::Set)
+                        .value_parser(value_parser!(PathBuf)),
+                )
+                .arg(
+                    Arg::new(CONVERT_OUTPUT)
+                        .value_name("OUTPUT")
+                        .help("the file to convert into.")
+                        .required(true)
+                        .action(ArgAction::Set)
+                        .value_parser(value_parser!(PathBuf)),
+                ),
+        )
+        .subcommand(
+            Command::new(STATS)
+                .about("Shows statistics of memory palaces.")
+                .arg(
+                    Arg::new(STATS_INPUTS)
+                        .value_name("INPUT")
+                        .help("the files, directories or workspace manifests of memory palaces.")
+                        .required(true)
+                        .action(ArgAction::Set)
+                        .num_args(1..)
+                        .value_parser(value_parser!(PathBuf)),
+                ),
+        )
+        .subcommand(status_command(SUSPEND, "Takes items out of reviews until activated."))
+        .subcommand(
+            status_command(BURY, "Takes items out of reviews until the next day.").arg(
+                Arg::new(BURY_FOR)
+                    .value_name("DURATION")
+                    .help("buries items for <DURATION>, e.g., 12h or 3d, instead of until the next day.")
+                    .long("for")
+                    .action(ArgAction::Set)
+                    .value_parser(parse_duration),
+            ),
+        )
+        .subcommand(status_command(ARCHIVE, "Takes items out of reviews for good, keeping them in files."))
+        .subcommand(status_command(ACTIVATE, "Takes items back into reviews."))
+        .subcommand(
+            Command::new(UNDO)
+                .about("Restores items as they were before the last exam session.")
+                .arg(
+                    Arg::new(UNDO_INPUTS)
+                        .value_name("INPUT")
+                        .help("the files, directories or workspace manifests examined.")
+                        .required(true)
+                        .action(ArgAction::Set)
+                        .num_args(1..)
+                        .value_parser(value_parser!(PathBuf)),
+                )
+                .arg(
+                    Arg::new(UNDO_SESSION)
+                        .value_name("ID")
+                        .help("undoes the session of <ID> instead of the last one.")
+                        .long("session")
+                        .action(ArgAction::Set)
+                        .value_parser(parse_sheet_id),
+                )
+                .arg(
+                    Arg::new(UNDO_LIST)
+                        .help("lists sessions instead of undoing.")
+                        .long("list")
+                        .conflicts_with(UNDO_SESSION)
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new(UNDO_DRY_RUN)
+                        .help("prints the items to be restored without writing.")
+                        .long("dry-run")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new(SYNC)
+                .about("Synchronizes memory palaces with their copies in a shared directory.")
+                .arg(
+                    Arg::new(SYNC_LOCALS)
+                        .value_name("LOCAL")
+                        .help("the files, directories or workspace manifests of local memory palaces.")
+                        .required(true)
+                        .action(ArgAction::Set)
+                        .num_args(1..)
+                        .value_parser(value_parser!(PathBuf)),
+                )
+                .arg(
+                    Arg::new(SYNC_SHARED)
+                        .value_name("DIR")
+                        .help("the shared directory where copies of memory palaces are.")
+                        .long("shared")
+                        .required(true)
+                        .action(ArgAction::Set)
+                        .value_parser(value_parser!(PathBuf)),
                 ),
         )
         .subcommand(
@@ -164,7 +871,7 @@ fn parse_args() -> Args {
                 .arg(
                     Arg::new(UPDATE_INTO)
                         .value_name("INTO")
-                        .help("the files to be merged into.")
+                        .help("the files, directories or workspace manifests to be merged into.")
                         .long("into")
                         .required(true)
                         .action(ArgAction::Set)
@@ -179,6 +886,26 @@ fn parse_args() -> Args {
                         .action(ArgAction::Set)
                         .num_args(1)
                         .value_parser(value_parser!(PathBuf)),
+                )
+                .arg(
+                    Arg::new(UPDATE_BASE)
+                        .value_name("BASE")
+                        .help("the file of items before they are changed, to merge contents in three ways.")
+                        .long("base")
+                        .action(ArgAction::Set)
+                        .value_parser(value_parser!(PathBuf)),
+                )
+                .arg(
+                    Arg::new(UPDATE_DRY_RUN)
+                        .help("prints planned changes as a diff without writing back.")
+                        .long("dry-run")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new(UPDATE_REMOVE_MERGED)
+                        .help("removes merged items from FROM, leaving only the rest there.")
+                        .long("remove-merged")
+                        .action(ArgAction::SetTrue),
                 ),
         )
         .subcommand(
@@ -195,58 +922,287 @@ fn parse_args() -> Args {
         );
     let matches = cmd.clone().get_matches();
 
-    if let Some(matches) = matches.subcommand_matches(COMPLETION) {
-        if let Some(sh) = matches
+    if let Some(matches) = matches.subcommand_matches(COMPLETION)
+        && let Some(sh) = matches
             .get_one::<completion::Shell>(COMPLETION_SHELL)
             .copied()
-        {
-            let cmd_name = cmd.get_name().to_string();
-            completion::generate(sh, &mut cmd, cmd_name, &mut std::io::stdout());
-            std::process::exit(0);
-        }
+    {
+        let cmd_name = cmd.get_name().to_string();
+        completion::generate(sh, &mut cmd, cmd_name, &mut std::io::stdout());
+        std::process::exit(0);
     }
     if let Some(matches) = matches.subcommand_matches(EXAM) {
-        let file_name = matches.get_one::<PathBuf>(EXAM_FILE_NAME).unwrap().clone();
+        let file_names: Vec<_> = matches
+            .get_many::<PathBuf>(EXAM_FILE_NAME)
+            .unwrap()
+            .cloned()
+            .collect();
         let take = matches.get_one::<usize>(EXAM_TAKE).copied();
+        let take_per_file = matches.get_one::<usize>(EXAM_TAKE_PER_FILE).copied();
         let dry_run = matches.get_flag(EXAM_DRY_RUN);
         let sort = matches.get_flag(EXAM_SORT);
+        let filter = Filter {
+            tags: matches
+                .get_many(EXAM_TAGS)
+                .map(|tags| tags.cloned().collect::<HashSet<_>>()),
+            query: matches.get_one::<String>(EXAM_QUERY).cloned(),
+            overdue: matches.get_one::<chrono::Duration>(EXAM_OVERDUE).copied(),
+            ..Filter::default()
+        };
+        let mut workspace = Workspace::open(&file_names);
+        for f in workspace.files.iter_mut() {
+            if let Some(n) = matches.get_one::<usize>(EXAM_NEW_PER_DAY) {
+                f.limits.new_per_day = Some(*n);
+            }
+            if let Some(n) = matches.get_one::<usize>(EXAM_REVIEWS_PER_DAY) {
+                f.limits.reviews_per_day = Some(*n);
+            }
+        }
         return Args::Exam(Exam {
-            file_name,
+            workspace,
             dry_run,
             take,
+            take_per_file,
+            sampling: sampling(matches),
             sort,
+            filter,
         });
     }
     if let Some(matches) = matches.subcommand_matches(SELECT) {
         let input = matches.get_one::<PathBuf>(SELECT_IN).unwrap().clone();
         let output = matches.get_one::<PathBuf>(SELECT_OUT).unwrap().clone();
         let take = matches.get_one::<usize>(SELECT_TAKE).copied();
-        let timeout = matches.get_flag(SELECT_TIMEOUT);
-        let tags = matches
-            .get_many(SELECT_TAGS)
-            .map(|tags| tags.cloned().collect::<HashSet<_>>());
+        let overdue = if matches.get_flag(SELECT_TIMEOUT) {
+            Some(chrono::Duration::zero())
+        } else {
+            matches.get_one::<chrono::Duration>(SELECT_OVERDUE).copied()
+        };
         let sort = matches.get_flag(SELECT_SORT);
+        let remove = matches.get_flag(SELECT_MOVE);
+        let mut input = Workspace::open(&[input]);
+        let filter = Filter {
+            tags: matches
+                .get_many(SELECT_TAGS)
+                .map(|tags| tags.cloned().collect::<HashSet<_>>()),
+            query: matches.get_one::<String>(SELECT_QUERY).cloned(),
+            overdue,
+            // leeches as told by schedulers of the workspace, without <N>
+            lapses: matches.value_source(SELECT_LEECH).map(|_| {
+                matches.get_one::<u32>(SELECT_LEECH).copied().unwrap_or(
+                    input
+                        .files
+                        .iter()
+                        .map(|x| x.scheduler.leech_lapses)
+                        .min()
+                        .unwrap_or(Scheduler::default().leech_lapses),
+                )
+            }),
+        };
+        if remove {
+            // Items are not moved from the output into itself.
+            let output = std::fs::canonicalize(&output).ok();
+            input
+                .files
+                .retain(|x| output.is_none() || std::fs::canonicalize(&x.path).ok() != output);
+        }
         return Args::Select(Select {
             input,
-            output,
+            output: open_store(&output),
             take,
-            timeout,
-            tags,
+            sampling: sampling(matches),
+            filter,
             sort,
+            remove,
+            dry_run: matches.get_flag(SELECT_DRY_RUN),
         });
     }
-    if let Some(matches) = matches.subcommand_matches(PRINT) {
-        if let Some(matches) = matches.subcommand_matches(PRINT_TYPST) {
-            let input = matches
-                .get_one::<PathBuf>(PRINT_TYPST_INPUT)
-                .unwrap()
-                .clone();
-            let output = matches
-                .get_one::<PathBuf>(PRINT_TYPST_OUTPUT)
-                .unwrap()
-                .clone();
-            return Args::Print(Print::Typst { input, output });
-        }
+    if let Some(matches) = matches.subcommand_matches(PRINT)
+        && let Some((format, matches)) = matches.subcommand()
+    {
+        let input = matches.get_one::<PathBuf>(PRINT_INPUT).unwrap().clone();
+        let selection = Selection {
+            input: Workspace::open(std::slice::from_ref(&input)),
+            filter: Filter {
+                tags: matches
+                    .get_many(PRINT_TAGS)
+                    .map(|tags| tags.cloned().collect::<HashSet<_>>()),
+                query: matches.get_one::<String>(PRINT_QUERY).cloned(),
+                ..Filter::default()
+            },
+            take: matches.get_one::<usize>(PRINT_TAKE).copied(),
+            shuffle: matches.get_flag(PRINT_SHUFFLE),
+            seed: matches.get_one::<u64>(PRINT_SEED).copied(),
+        };
+        let format = match format {
+            PRINT_TYPST => Format::Typst {
+                template: matches.get_one::<PathBuf>(PRINT_TEMPLATE).cloned(),
+            },
+            PRINT_PDF => Format::Pdf {
+                template: matches.get_one::<PathBuf>(PRINT_TEMPLATE).cloned(),
+            },
+            PRINT_MARKDOWN => Format::Markdown,
+            PRINT_HTML => Format::Html {
+                collapse: matches.get_flag(PRINT_COLLAPSE),
+            },
+            PRINT_CARDS => Format::Cards {
+                pdf: matches
+                    .get_one::<PathBuf>(PRINT_OUTPUT)
+                    .unwrap()
+                    .extension()
+                    .is_some_and(|x| x == "pdf"),
+                layout: CardLayout {
+                    columns: *matches.get_one::<u64>(PRINT_COLUMNS).unwrap() as usize,
+                    rows: *matches.get_one::<u64>(PRINT_ROWS).unwrap() as usize,
+                    width: *matches.get_one::<f64>(PRINT_CARD_WIDTH).unwrap(),
+                    height: *matches.get_one::<f64>(PRINT_CARD_HEIGHT).unwrap(),
+                },
+            },
+            _ => unreachable!(),
+        };
+        return Args::Print(Print {
+            selection,
+            format,
+            output: matches.get_one::<PathBuf>(PRINT_OUTPUT).unwrap().clone(),
+            questions_only: matches.get_flag(PRINT_QUESTIONS_ONLY),
+            answer_key: matches.get_one::<PathBuf>(PRINT_ANSWER_KEY).cloned(),
+            sheet: matches
+                .get_one::<String>(PRINT_SHEET)
+                .map(|id| (id.clone(), input)),
+            force: matches.get_flag(PRINT_FORCE),
+            check: matches.get_flag(PRINT_CHECK),
+        });
+    }
+    if let Some(matches) = matches.subcommand_matches(GRADE) {
+        let input = matches.get_one::<PathBuf>(GRADE_INPUT).unwrap().clone();
+        let sheet = matches.get_one::<String>(GRADE_SHEET).unwrap().clone();
+        let wrong = matches
+            .get_many::<usize>(GRADE_WRONG)
+            .map(|xs| xs.copied().collect())
+            .unwrap_or_default();
+        let dry_run = matches.get_flag(GRADE_DRY_RUN);
+        return Args::Grade(Grade {
+            input,
+            sheet,
+            wrong,
+            dry_run,
+        });
+    }
+    if let Some(matches) = matches.subcommand_matches(IMPORT)
+        && let Some((format, matches)) = matches.subcommand()
+    {
+        let format = match format {
+            TABLE_CSV => ImportFormat::Csv(table_options(matches)),
+            ANKI => ImportFormat::Anki {
+                keep_html: matches.get_flag(ANKI_KEEP_HTML),
+            },
+            _ => unreachable!(),
+        };
+        return Args::Import(Import {
+            input: matches.get_one::<PathBuf>(IMPORT_INPUT).unwrap().clone(),
+            into: matches.get_one::<PathBuf>(IMPORT_INTO).unwrap().clone(),
+            format,
+            merge: matches.get_flag(IMPORT_MERGE),
+        });
+    }
+    if let Some(matches) = matches.subcommand_matches(EXPORT)
+        && let Some((format, matches)) = matches.subcommand()
+    {
+        let output = matches.get_one::<PathBuf>(EXPORT_OUTPUT).unwrap().clone();
+        let format = match format {
+            TABLE_CSV => ExportFormat::Csv(table_options(matches)),
+            ANKI => ExportFormat::Anki {
+                deck: matches
+                    .get_one::<String>(ANKI_DECK)
+                    .cloned()
+                    .unwrap_or_else(|| output.file_stem().unwrap().to_string_lossy().into()),
+            },
+            _ => unreachable!(),
+        };
+        return Args::Export(Export {
+            input: matches.get_one::<PathBuf>(EXPORT_INPUT).unwrap().clone(),
+            output,
+            format,
+        });
+    }
+    if let Some(matches) = matches.subcommand_matches(CONVERT) {
+        let input = matches.get_one::<PathBuf>(CONVERT_INPUT).unwrap().clone();
+        let output = matches.get_one::<PathBuf>(CONVERT_OUTPUT).unwrap().clone();
+        return Args::Convert(Convert { input, output });
+    }
+    if let Some(matches) = matches.subcommand_matches(STATS) {
+        let inputs: Vec<_> = matches
+            .get_many::<PathBuf>(STATS_INPUTS)
+            .unwrap()
+            .cloned()
+            .collect();
+        return Args::Stats(Stats { inputs });
+    }
+    if let Some((name, matches)) = matches.subcommand()
+        && [SUSPEND, BURY, ARCHIVE, ACTIVATE].contains(&name)
+    {
+        let inputs: Vec<_> = matches
+            .get_many::<PathBuf>(STATUS_INPUTS)
+            .unwrap()
+            .cloned()
+            .collect();
+        let workspace = Workspace::open(&inputs);
+        let now = chrono::Utc::now();
+        let status = match name {
+            SUSPEND => Status::Suspended,
+            BURY => Status::Buried(match matches.get_one::<chrono::Duration>(BURY_FOR) {
+                Some(x) => now + *x,
+                None => {
+                    // the day boundary of the first file
+                    let boundary = workspace
+                        .files
+                        .first()
+                        .map(|x| x.scheduler.boundary)
+                        .unwrap_or_default();
+                    boundary.start(boundary.day(&now).succ_opt().unwrap())
+                }
+            }),
+            ARCHIVE => Status::Archived,
+            ACTIVATE => Status::Active,
+            _ => unreachable!(),
+        };
+        return Args::SetStatus(SetStatus {
+            workspace,
+            status,
+            ids: matches
+                .get_many::<String>(STATUS_IDS)
+                .map(|xs| xs.cloned().collect())
+                .unwrap_or_default(),
+            filter: Filter {
+                tags: matches
+                    .get_many(STATUS_TAGS)
+                    .map(|tags| tags.cloned().collect::<HashSet<_>>()),
+                query: matches.get_one::<String>(STATUS_QUERY).cloned(),
+                ..Filter::default()
+            },
+            dry_run: matches.get_flag(STATUS_DRY_RUN),
+        });
+    }
+    if let Some(matches) = matches.subcommand_matches(UNDO) {
+        let inputs: Vec<_> = matches
+            .get_many::<PathBuf>(UNDO_INPUTS)
+            .unwrap()
+            .cloned()
+            .collect();
+        return Args::Undo(Undo {
+            workspace: Workspace::open(&inputs),
+            session: matches.get_one::<String>(UNDO_SESSION).cloned(),
+            list: matches.get_flag(UNDO_LIST),
+            dry_run: matches.get_flag(UNDO_DRY_RUN),
+        });
+    }
+    if let Some(matches) = matches.subcommand_matches(SYNC) {
+        let locals: Vec<_> = matches
+            .get_many::<PathBuf>(SYNC_LOCALS)
+            .unwrap()
+            .cloned()
+            .collect();
+        let shared = matches.get_one::<PathBuf>(SYNC_SHARED).unwrap().clone();
+        return Args::Sync(Synchronize { locals, shared });
     }
     if let Some(matches) = matches.subcommand_matches(UPDATE) {
         let into: Vec<_> = matches
@@ -255,14 +1211,30 @@ fn parse_args() -> Args {
             .cloned()
             .collect();
         let from = matches.get_one::<PathBuf>(UPDATE_FROM).unwrap().clone();
-        return Args::Update(Update { into, from });
+        let base = matches.get_one::<PathBuf>(UPDATE_BASE).cloned();
+        let dry_run = matches.get_flag(UPDATE_DRY_RUN);
+        return Args::Update(Update {
+            into: Workspace::open(&into),
+            from: open_store(&from),
+            base: base.map(|x| open_store(&x)),
+            dry_run,
+            remove_merged: matches.get_flag(UPDATE_REMOVE_MERGED),
+        });
     }
     unreachable!()
 }
 
 enum Args {
+    Convert(Convert),
     Exam(Exam),
+    Export(Export),
+    Import(Import),
+    Grade(Grade),
     Select(Select),
     Print(Print),
+    Stats(Stats),
+    SetStatus(SetStatus),
+    Sync(Synchronize),
+    Undo(Undo),
     Update(Update),
 }
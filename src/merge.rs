@@ -0,0 +1,196 @@
+use crate::{Item, file::fmt_time};
+use std::fmt::Write;
+
+pub(crate) enum Merged {
+    Unchanged,
+    Changed(Item),
+    /// the merged item, where conflicting fields are kept as in `into`,
+    /// and names of conflicting fields
+    Conflict(Item, Vec<&'static str>),
+}
+
+/// Merges `from` into `into`.
+///
+/// Scheduling fields come together from the side checked more recently.
+/// Contents, i.e., the answer, the tag and the status, are merged field by field against
+/// `base`, the item before both sides changed.
+/// A field changed on both sides differently is a conflict.
+/// Without `base`, every field of different contents is a conflict.
+pub(crate) fn merge(into: &Item, from: &Item, base: Option<&Item>) -> Merged {
+    let mut res = into.clone();
+    let mut conflicts = vec![];
+
+    match merge_field(base.map(|x| &x.answer), &into.answer, &from.answer) {
+        Some(x) => res.answer = x.clone(),
+        None => conflicts.push("a"),
+    }
+    match merge_field(base.map(|x| &x.tag), &into.tag, &from.tag) {
+        Some(x) => res.tag = x.clone(),
+        None => conflicts.push("tag"),
+    }
+    match merge_field(base.map(|x| &x.status), &into.status, &from.status) {
+        Some(x) => res.status = *x,
+        None => conflicts.push("status"),
+    }
+
+    if from.last_check_time > into.last_check_time {
+        res.first_remember_time = from.first_remember_time;
+        res.last_check_time = from.last_check_time;
+        res.due_time = from.due_time;
+        res.reviews = from.reviews;
+        res.lapses = from.lapses;
+    }
+
+    if !conflicts.is_empty() {
+        Merged::Conflict(res, conflicts)
+    } else if res == *into {
+        Merged::Unchanged
+    } else {
+        Merged::Changed(res)
+    }
+}
+
+fn merge_field<'a, T: PartialEq>(base: Option<&T>, into: &'a T, from: &'a T) -> Option<&'a T> {
+    if into == from || base == Some(from) {
+        Some(into)
+    } else if base == Some(into) {
+        Some(from)
+    } else {
+        None
+    }
+}
+
+/// Describes changes from `old` to `new` line by line, in the style of unified diffs.
+pub(crate) fn diff(old: &Item, new: &Item) -> String {
+    let mut buf = String::new();
+    diff_field(&mut buf, "a", &old.answer, &new.answer);
+    diff_field(&mut buf, "tag", &fmt_opt(&old.tag), &fmt_opt(&new.tag));
+    diff_field(&mut buf, "status", old.status.name(), new.status.name());
+    diff_field(
+        &mut buf,
+        "buried-until",
+        &fmt_time(&old.status.buried_until()),
+        &fmt_time(&new.status.buried_until()),
+    );
+    diff_field(
+        &mut buf,
+        "first-remember-time",
+        &fmt_time(&old.first_remember_time),
+        &fmt_time(&new.first_remember_time),
+    );
+    diff_field(
+        &mut buf,
+        "last-check-time",
+        &fmt_time(&old.last_check_time),
+        &fmt_time(&new.last_check_time),
+    );
+    diff_field(
+        &mut buf,
+        "due-time",
+        &fmt_time(&old.due_time),
+        &fmt_time(&new.due_time),
+    );
+    diff_field(
+        &mut buf,
+        "reviews",
+        &old.reviews.to_string(),
+        &new.reviews.to_string(),
+    );
+    diff_field(
+        &mut buf,
+        "lapses",
+        &old.lapses.to_string(),
+        &new.lapses.to_string(),
+    );
+    buf
+}
+
+fn diff_field(buf: &mut String, name: &str, old: &str, new: &str) {
+    if old == new {
+        return;
+    }
+    writeln!(buf, "-{} = {:?}", name, old).unwrap();
+    writeln!(buf, "+{} = {:?}", name, new).unwrap();
+}
+
+fn fmt_opt(x: &Option<String>) -> String {
+    x.clone().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::prelude::*;
+
+    fn item(answer: &str, tag: Option<&str>) -> Item {
+        Item {
+            tag: tag.map(|x| x.to_string()),
+            ..Item::new("Q?", answer)
+        }
+    }
+
+    #[test]
+    fn merge_field_against_base() {
+        // changed on one side
+        assert_eq!(merge_field(Some(&1), &1, &2), Some(&2));
+        assert_eq!(merge_field(Some(&1), &2, &1), Some(&2));
+        // changed on both sides equally
+        assert_eq!(merge_field(Some(&1), &2, &2), Some(&2));
+        // changed on both sides differently
+        assert_eq!(merge_field(Some(&1), &2, &3), None);
+    }
+
+    #[test]
+    fn merge_field_without_base() {
+        assert_eq!(merge_field(None, &1, &1), Some(&1));
+        assert_eq!(merge_field(None, &1, &2), None);
+    }
+
+    #[test]
+    fn merge_with_base() {
+        let base = item("a", Some("x"));
+        let into = item("a", Some("y"));
+        let from = item("b", Some("x"));
+        let Merged::Changed(res) = merge(&into, &from, Some(&base)) else {
+            panic!("not changed");
+        };
+        assert_eq!(res.answer, "b");
+        assert_eq!(res.tag.as_deref(), Some("y"));
+
+        assert!(matches!(
+            merge(&into, &base, Some(&base)),
+            Merged::Unchanged
+        ));
+    }
+
+    #[test]
+    fn merge_without_base() {
+        let into = item("a", None);
+        assert!(matches!(merge(&into, &into, None), Merged::Unchanged));
+
+        let from = item("b", None);
+        let Merged::Conflict(res, fields) = merge(&into, &from, None) else {
+            panic!("no conflict");
+        };
+        assert_eq!(res.answer, "a");
+        assert_eq!(fields, vec!["a"]);
+    }
+
+    #[test]
+    fn merge_conflict() {
+        let base = item("a", Some("x"));
+        let into = item("b", Some("y"));
+        let mut from = item("c", Some("z"));
+        from.last_check_time = Some(Utc.with_ymd_and_hms(2024, 1, 2, 3, 4, 5).unwrap());
+        from.reviews = 3;
+        let Merged::Conflict(res, fields) = merge(&into, &from, Some(&base)) else {
+            panic!("no conflict");
+        };
+        // Contents are kept as `into`, but scheduling comes from the newer side.
+        assert_eq!(res.answer, "b");
+        assert_eq!(res.tag.as_deref(), Some("y"));
+        assert_eq!(res.last_check_time, from.last_check_time);
+        assert_eq!(res.reviews, 3);
+        assert_eq!(fields, vec!["a", "tag"]);
+    }
+}
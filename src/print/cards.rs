@@ -0,0 +1,52 @@
+use super::{Header, compile_pdf, typst_source};
+use crate::*;
+use std::path::PathBuf;
+
+/// the template printing items in double-sided cards.
+const CARDS_TEMPLATE: &str = include_str!("cards.typ");
+
+/// How cards are laid out in a page.
+#[derive(Debug, Clone)]
+pub struct CardLayout {
+    pub columns: usize,
+    pub rows: usize,
+    /// in millimeters
+    pub width: f64,
+    /// in millimeters
+    pub height: f64,
+}
+
+impl Default for CardLayout {
+    fn default() -> Self {
+        Self {
+            columns: 2,
+            rows: 4,
+            width: 85.0,
+            height: 55.0,
+        }
+    }
+}
+
+/// Prints items in double-sided cards, where questions are on front pages
+/// and answers on back pages.
+///
+/// Back pages are mirrored, so every answer is at the back of its question
+/// after duplex printing and cutting.
+/// It prints a PDF document with `pdf`, or the typst source otherwise.
+pub(super) fn cards(items: &[Item], header: &Header, layout: &CardLayout, pdf: bool) -> Vec<u8> {
+    let template = format!(
+        "\
+#let card-columns = {}
+#let card-rows = {}
+#let card-width = {}mm
+#let card-height = {}mm
+{}",
+        layout.columns, layout.rows, layout.width, layout.height, CARDS_TEMPLATE
+    );
+    let source = typst_source(items, header, &template);
+    if pdf {
+        compile_pdf(source, PathBuf::from("."))
+    } else {
+        source.into_bytes()
+    }
+}
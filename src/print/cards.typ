@@ -0,0 +1,36 @@
+#set page(margin: 1cm)
+#let per-page = card-columns * card-rows
+#let card(body, stroke: 0.5pt + gray) = box(
+  width: card-width,
+  height: card-height,
+  stroke: stroke,
+  inset: 4mm,
+  align(center + horizon, body),
+)
+#let sheet(cells) = align(center, grid(
+  columns: (card-width,) * card-columns,
+  rows: (card-height,) * card-rows,
+  ..cells,
+))
+#for start in range(0, items.len(), step: per-page) {
+  let chunk = items.slice(start, calc.min(start + per-page, items.len()))
+  if start > 0 {
+    pagebreak()
+  }
+  // fronts in reading order
+  sheet(range(per-page).map(i => if i < chunk.len() {
+    card(chunk.at(i).q)
+  } else {
+    card([])
+  }))
+  pagebreak()
+  // backs mirrored left to right, for duplex printing flipped on the long edge
+  sheet(range(per-page).map(i => {
+    let j = calc.quo(i, card-columns) * card-columns + card-columns - 1 - calc.rem(i, card-columns)
+    if j < chunk.len() {
+      card(chunk.at(j).a, stroke: none)
+    } else {
+      card([], stroke: none)
+    }
+  }))
+}
@@ -0,0 +1,7 @@
+#if sheet != none [Sheet: #sheet]
+
+#table(
+  columns: (1cm, auto, auto),
+  table.header([], [*Q*], [*A*]),
+  ..items.map(x => ([#x.no], x.q, x.a)).flatten(),
+)
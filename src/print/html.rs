@@ -0,0 +1,76 @@
+use super::Header;
+use crate::*;
+use std::fmt::Write;
+
+const STYLE: &str = "\
+body { font-family: sans-serif; max-width: 50em; margin: auto; padding: 1em; }
+dt { font-weight: bold; margin-top: 1em; }
+dd { margin-left: 2em; }
+dt, dd { white-space: pre-wrap; }
+summary { cursor: pointer; color: gray; }
+";
+
+/// Prints items into a self-contained HTML file, in a definition list.
+///
+/// With `collapse`, answers are hidden until clicked.
+pub(super) fn html(items: &[Item], header: &Header, collapse: bool) -> String {
+    let title = escape(header.title);
+    let mut buf = String::new();
+    writeln!(
+        &mut buf,
+        "\
+<!DOCTYPE html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+<title>{title}</title>
+<style>
+{STYLE}</style>
+</head>
+<body>
+<h1>{title}</h1>"
+    )
+    .unwrap();
+    if let Some(sheet) = header.sheet {
+        writeln!(&mut buf, "<p>Sheet: {}</p>", escape(sheet)).unwrap();
+    }
+    writeln!(&mut buf, "<dl>").unwrap();
+    for (i, item) in items.iter().enumerate() {
+        writeln!(
+            &mut buf,
+            "<dt>{}. {}</dt>",
+            i + 1,
+            escape(item.question.trim())
+        )
+        .unwrap();
+        let answer = escape(item.answer.trim());
+        if collapse {
+            writeln!(
+                &mut buf,
+                "<dd><details><summary>A</summary>{}</details></dd>",
+                answer
+            )
+            .unwrap();
+        } else {
+            writeln!(&mut buf, "<dd>{}</dd>", answer).unwrap();
+        }
+    }
+    writeln!(&mut buf, "</dl>\n</body>\n</html>").unwrap();
+    buf
+}
+
+fn escape(text: &str) -> String {
+    let mut res = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => res.push_str("&amp;"),
+            '<' => res.push_str("&lt;"),
+            '>' => res.push_str("&gt;"),
+            '"' => res.push_str("&quot;"),
+            '\'' => res.push_str("&#39;"),
+            '\r' => {}
+            _ => res.push(c),
+        }
+    }
+    res
+}
@@ -1,17 +1,209 @@
 use super::*;
-use std::path::PathBuf;
+use crate::*;
+use chrono::prelude::*;
+use log::*;
+use rand::{SeedableRng, prelude::*};
+use rand_chacha::ChaCha8Rng;
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+pub struct Print<S: Store = Box<dyn Store>> {
+    /// which items to print
+    pub selection: Selection<S>,
+
+    pub format: Format,
+
+    /// the file to be printed
+    pub output: PathBuf,
+
+    /// Prints questions only, leaving answers blank.
+    pub questions_only: bool,
+
+    /// the file to print the answer key into, in the same format and numbering
+    pub answer_key: Option<PathBuf>,
+
+    /// the ID to record the sheet by, and the file, directory or workspace
+    /// manifest beside which it is recorded, so grades can be entered afterwards
+    pub sheet: Option<(String, PathBuf)>,
+
+    /// Records the sheet even if one of the same ID exists, which is replaced.
+    pub force: bool,
+
+    /// Records printed items on the sheet as checked now,
+    /// which takes effect when grades are entered.
+    pub check: bool,
+}
+
+/// Information about a printed sheet besides items.
+#[derive(Debug, Clone, Default)]
+pub struct Header<'a> {
+    /// the title of HTML pages
+    pub title: &'a str,
+    /// tags to select items
+    pub tags: Option<&'a HashSet<String>>,
+    /// the ID of the sheet, if recorded
+    pub sheet: Option<&'a str>,
+}
+
+/// Which items to print, and in which order.
+pub struct Selection<S: Store = Box<dyn Store>> {
+    /// the memory palace to print
+    pub input: Workspace<S>,
+
+    /// Prints only items meeting the conditions
+    pub filter: Filter,
+
+    /// Prints at most N items.
+    pub take: Option<usize>,
+
+    /// Shuffles items before taking.
+    pub shuffle: bool,
+
+    /// the seed to shuffle items, for reproducible worksheets
+    pub seed: Option<u64>,
+}
+
+/// Items of every file, and positions of picked ones in the order to print.
+struct Picked {
+    items: Vec<Vec<Item>>,
+    picked: Vec<(usize, usize)>,
+}
 
 #[derive(Debug)]
-pub enum Print {
-    Typst { input: PathBuf, output: PathBuf },
+pub enum Format {
+    Typst {
+        /// the typst template to print items, instead of the default table
+        template: Option<PathBuf>,
+    },
+    Pdf {
+        /// the typst template to print items, instead of the default table
+        template: Option<PathBuf>,
+    },
+    Markdown,
+    Html {
+        /// Hides answers until they are clicked
+        collapse: bool,
+    },
+    Cards {
+        layout: CardLayout,
+        /// Prints a PDF document, instead of the typst source.
+        pdf: bool,
+    },
 }
 
-impl Print {
+impl<S: Store> Print<S> {
     pub fn gogogo(&self) {
+        if let Some((id, input)) = &self.sheet
+            && Sheet::exists(input, id)
+            && !self.force
+        {
+            error!("sheet {} exists, and is replaced only by --force.", id);
+            return;
+        }
+        let now = Utc::now();
+        let picked = self.selection.pick(&now);
+        let items: Vec<_> = picked
+            .picked
+            .iter()
+            .map(|(f, i)| picked.items[*f][*i].clone())
+            .collect();
+        let header = Header {
+            title: "",
+            tags: self.selection.filter.tags.as_ref(),
+            sheet: self.sheet.as_ref().map(|(id, _)| id.as_str()),
+        };
+        if self.questions_only {
+            let questions: Vec<_> = items
+                .iter()
+                .cloned()
+                .map(|mut x| {
+                    x.answer.clear();
+                    x
+                })
+                .collect();
+            self.format.print(&questions, &header, &self.output);
+        } else {
+            self.format.print(&items, &header, &self.output);
+        }
+        if let Some(answer_key) = &self.answer_key {
+            self.format.print(&items, &header, answer_key);
+        }
+        if let Some((id, input)) = &self.sheet {
+            let sheet = Sheet {
+                id: id.clone(),
+                print_time: now,
+                check_time: self.check.then_some(now),
+                grade_time: None,
+                items: picked
+                    .picked
+                    .iter()
+                    .zip(items.iter())
+                    .map(|((f, _), item)| {
+                        let file = &self.selection.input.files[*f].path;
+                        SheetItem {
+                            file: std::fs::canonicalize(file).unwrap_or(file.clone()),
+                            question: item.question.clone(),
+                        }
+                    })
+                    .collect(),
+            };
+            sheet.write(input);
+            info!("sheet {} is recorded.", id);
+        }
+    }
+}
+
+impl Format {
+    /// Prints items into a document in memory.
+    pub fn render(&self, items: &[Item], header: &Header) -> Vec<u8> {
         match self {
-            Print::Typst { input, output } => {
-                typst(input, output);
-            }
+            Format::Typst { template } => typst(items, header, template.as_deref()).into_bytes(),
+            Format::Pdf { template } => pdf(items, header, template.as_deref()),
+            Format::Markdown => markdown(items, header).into_bytes(),
+            Format::Html { collapse } => html(items, header, *collapse).into_bytes(),
+            Format::Cards { layout, pdf } => cards(items, header, layout, *pdf),
+        }
+    }
+
+    fn print(&self, items: &[Item], header: &Header, output: &Path) {
+        let title = output.file_stem().unwrap_or_default().to_string_lossy();
+        let header = Header {
+            title: &title,
+            ..header.clone()
+        };
+        std::fs::write(output, self.render(items, &header)).unwrap();
+    }
+}
+
+impl<S: Store> Selection<S> {
+    /// Picks items to print, in the order to print.
+    fn pick(&self, now: &UtcTime) -> Picked {
+        let items = self.input.read();
+        let mut picked: Vec<_> = items
+            .iter()
+            .enumerate()
+            .flat_map(|(f, xs)| {
+                xs.iter()
+                    .enumerate()
+                    .filter(|(_, x)| self.filter.matches(x, now))
+                    .map(move |(i, _)| (f, i))
+            })
+            .collect();
+        if self.shuffle {
+            let mut rng = match self.seed {
+                Some(seed) => ChaCha8Rng::seed_from_u64(seed),
+                None => ChaCha8Rng::from_rng(&mut rand::rng()),
+            };
+            picked.shuffle(&mut rng);
+        }
+        if let Some(n) = self.take {
+            info!("take {n} out of {} items.", picked.len());
+            picked.truncate(n);
+        } else {
+            info!("take all {} items.", picked.len());
         }
+        Picked { items, picked }
     }
 }
@@ -0,0 +1,41 @@
+use super::Header;
+use crate::*;
+use std::fmt::Write;
+
+/// Prints items in a markdown table.
+pub(super) fn markdown(items: &[Item], header: &Header) -> String {
+    let mut buf = String::new();
+    if let Some(sheet) = header.sheet {
+        writeln!(&mut buf, "Sheet: {}\n", escape(sheet)).unwrap();
+    }
+    writeln!(&mut buf, "| | Q | A |").unwrap();
+    writeln!(&mut buf, "|---:|---|---|").unwrap();
+    for (i, item) in items.iter().enumerate() {
+        writeln!(
+            &mut buf,
+            "| {} | {} | {} |",
+            i + 1,
+            escape(item.question.trim()),
+            escape(item.answer.trim())
+        )
+        .unwrap();
+    }
+    buf
+}
+
+/// Escapes text into a cell of a markdown table, where line breaks are kept.
+fn escape(text: &str) -> String {
+    let mut res = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\r' => {}
+            '\n' => res.push_str("<br>"),
+            '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '#' | '|' | '~' | '&' | '$' => {
+                res.push('\\');
+                res.push(c);
+            }
+            _ => res.push(c),
+        }
+    }
+    res
+}
@@ -1,5 +1,13 @@
+mod cards;
+mod html;
 mod r#impl;
+mod markdown;
+mod pdf;
 mod typst;
 
+pub use self::cards::*;
+use self::html::*;
 pub use self::r#impl::*;
+use self::markdown::*;
+use self::pdf::*;
 use self::typst::*;
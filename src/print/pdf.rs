@@ -0,0 +1,119 @@
+use super::{
+    Header,
+    typst::{read_template, typst_source},
+};
+use crate::*;
+use chrono::prelude::*;
+use std::path::{Path, PathBuf};
+use typst::{
+    Library, LibraryExt, World,
+    diag::{FileError, FileResult},
+    foundations::{Bytes, Datetime},
+    syntax::{FileId, RootedPath, Source, VirtualPath, VirtualRoot},
+    text::{Font, FontBook},
+    utils::LazyHash,
+};
+use typst_kit::fonts::FontStore;
+use typst_layout::PagedDocument;
+
+/// Prints items into a PDF document, by compiling the typst source in process.
+///
+/// Fonts are bundled, so it works offline.
+pub(super) fn pdf(items: &[Item], header: &Header, template: Option<&Path>) -> Vec<u8> {
+    let source = typst_source(items, header, &read_template(template));
+    // files used by the template are relative to it
+    let root = match template.and_then(|x| x.parent()) {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    compile_pdf(source, root)
+}
+
+/// Compiles typst source into a PDF document,
+/// where files used by the source are relative to `root`.
+pub(super) fn compile_pdf(source: String, root: PathBuf) -> Vec<u8> {
+    let world = PrintWorld::new(source, root);
+    let document = typst::compile::<PagedDocument>(&world)
+        .output
+        .unwrap_or_else(|errs| {
+            let msgs: Vec<_> = errs.iter().map(|x| x.message.to_string()).collect();
+            panic!("fail to compile typst: {}", msgs.join("; "));
+        });
+    typst_pdf::pdf(&document, &typst_pdf::PdfOptions::default()).unwrap_or_else(|errs| {
+        let msgs: Vec<_> = errs.iter().map(|x| x.message.to_string()).collect();
+        panic!("fail to export PDF: {}", msgs.join("; "));
+    })
+}
+
+struct PrintWorld {
+    library: LazyHash<Library>,
+    fonts: FontStore,
+    main: Source,
+    root: PathBuf,
+}
+
+impl PrintWorld {
+    fn new(source: String, root: PathBuf) -> Self {
+        let mut fonts = FontStore::new();
+        fonts.extend(typst_kit::fonts::embedded());
+        fonts.extend(Font::iter(Bytes::new(CJK_FONT)).map(|font| {
+            let info = font.info().clone();
+            (font, info)
+        }));
+        let main = RootedPath::new(VirtualRoot::Project, VirtualPath::new("main.typ").unwrap());
+        Self {
+            library: LazyHash::new(Library::default()),
+            fonts,
+            main: Source::new(main.intern(), source),
+            root,
+        }
+    }
+
+    fn read(&self, id: FileId) -> FileResult<Vec<u8>> {
+        let path = id.vpath().realize(&self.root)?;
+        std::fs::read(&path).map_err(|e| FileError::from_io(e, &path))
+    }
+}
+
+impl World for PrintWorld {
+    fn library(&self) -> &LazyHash<Library> {
+        &self.library
+    }
+
+    fn book(&self) -> &LazyHash<FontBook> {
+        self.fonts.book()
+    }
+
+    fn main(&self) -> FileId {
+        self.main.id()
+    }
+
+    fn source(&self, id: FileId) -> FileResult<Source> {
+        if id == self.main.id() {
+            return Ok(self.main.clone());
+        }
+        let buf = self.read(id)?;
+        Ok(Source::new(id, String::from_utf8(buf)?))
+    }
+
+    fn file(&self, id: FileId) -> FileResult<Bytes> {
+        if id == self.main.id() {
+            return Ok(Bytes::from_string(self.main.clone()));
+        }
+        Ok(Bytes::new(self.read(id)?))
+    }
+
+    fn font(&self, index: usize) -> Option<Font> {
+        self.fonts.font(index)
+    }
+
+    fn today(&self, offset: Option<typst::foundations::Duration>) -> Option<Datetime> {
+        let now = match offset {
+            Some(offset) => {
+                Utc::now().naive_utc() + chrono::Duration::seconds(offset.seconds() as i64)
+            }
+            None => Local::now().naive_local(),
+        };
+        Datetime::from_ymd(now.year(), now.month() as u8, now.day() as u8)
+    }
+}
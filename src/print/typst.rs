@@ -1,28 +1,108 @@
+use super::Header;
 use crate::*;
+use chrono::prelude::*;
 use std::{fmt::Write, path::Path};
 
-pub(super) fn typst(input: &Path, output: &Path) {
-    let items = read_file(input);
+/// the template printing items in a table.
+const DEFAULT_TEMPLATE: &str = include_str!("default.typ");
+
+/// Prints items by a template in typst.
+///
+/// The template is typst code, preceded by definitions of
+/// * `items`: an array of dictionaries with `no`, the 1-based number, and
+///   `q` and `a`, the question and the answer in content.
+/// * `date`: the date of printing.
+/// * `tags`: an array of tags to select items, or `none`.
+/// * `sheet`: the ID of the recorded sheet, or `none`.
+pub(super) fn typst(items: &[Item], header: &Header, template: Option<&Path>) -> String {
+    typst_source(items, header, &read_template(template))
+}
+
+/// Reads the template, or gets the default one.
+pub(super) fn read_template(template: Option<&Path>) -> String {
+    match template {
+        Some(f) => std::fs::read_to_string(f).unwrap(),
+        None => DEFAULT_TEMPLATE.to_string(),
+    }
+}
+
+/// Generates the typst source of items by the template.
+pub(super) fn typst_source(items: &[Item], header: &Header, template: &str) -> String {
     let mut buf = String::new();
+    let date = Utc::now().date_naive();
     writeln!(
         &mut buf,
-        "\
-#table(
-    columns: (1cm, auto, auto),
-    table.header([], [*Q*], [*A*]),
-"
+        "#let date = datetime(year: {}, month: {}, day: {})",
+        date.year(),
+        date.month(),
+        date.day()
     )
     .unwrap();
+    match header.tags {
+        Some(tags) => {
+            let mut tags: Vec<_> = tags.iter().collect();
+            tags.sort();
+            write!(&mut buf, "#let tags = (").unwrap();
+            for tag in tags.iter() {
+                write!(&mut buf, "{:?}, ", tag).unwrap();
+            }
+            writeln!(&mut buf, ")").unwrap();
+        }
+        None => writeln!(&mut buf, "#let tags = none").unwrap(),
+    }
+    match header.sheet {
+        Some(sheet) => writeln!(&mut buf, "#let sheet = {:?}", sheet).unwrap(),
+        None => writeln!(&mut buf, "#let sheet = none").unwrap(),
+    }
+    writeln!(&mut buf, "#let items = (").unwrap();
     for (i, item) in items.iter().enumerate() {
         writeln!(
             &mut buf,
-            "    [{}], [{}], [{}],",
+            "  (no: {}, q: [{}], a: [{}]),",
             i + 1,
-            item.question.trim().replace("\n", "\\\n"),
-            item.answer.trim().replace("\n", "\\\n")
+            escape(item.question.trim()),
+            escape(item.answer.trim())
         )
         .unwrap();
     }
     writeln!(&mut buf, ")").unwrap();
-    std::fs::write(output, buf).unwrap();
+    buf.push_str(template);
+    buf
+}
+
+/// Escapes text into typst markup, where line breaks are kept.
+pub(super) fn escape(text: &str) -> String {
+    let mut res = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\r' => {}
+            '\n' => res.push_str("\\\n"),
+            '\\' | '/' | '#' | '[' | ']' | '{' | '}' | '*' | '_' | '`' | '$' | '<' | '>' | '@'
+            | '~' | '=' | '-' | '+' | '.' | '\'' | '"' => {
+                res.push('\\');
+                res.push(c);
+            }
+            _ => res.push(c),
+        }
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape_markup() {
+        assert_eq!(escape("plain text"), "plain text");
+        assert_eq!(escape("#let x = $y$"), "\\#let x \\= \\$y\\$");
+        assert_eq!(escape("*a* _b_ `c`"), "\\*a\\* \\_b\\_ \\`c\\`");
+        assert_eq!(escape("a // b"), "a \\/\\/ b");
+    }
+
+    #[test]
+    fn escape_line_breaks() {
+        assert_eq!(escape("a\r\nb"), "a\\\nb");
+        assert_eq!(escape("a\nb"), "a\\\nb");
+    }
 }
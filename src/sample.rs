@@ -0,0 +1,148 @@
+use crate::*;
+use log::*;
+use rand::prelude::*;
+
+/// How to take at most N items out of candidates.
+#[derive(Debug, Clone, Default)]
+pub struct Sampling {
+    pub order: SampleOrder,
+
+    /// Takes at most so many items of every tag.
+    pub quotas: Vec<(String, Quota)>,
+}
+
+/// Which items to take first.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SampleOrder {
+    /// uniformly at random
+    #[default]
+    Random,
+    /// the most overdue first, and new items last
+    MostOverdue,
+    /// new items first in their order in files, and then the most overdue
+    OldestNew,
+    /// at random, where more overdue items are more likely
+    Weighted,
+}
+
+/// How many items of a tag to take at most.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Quota {
+    Count(usize),
+    /// a proportion of items to take, from 0 to 1
+    Share(f64),
+}
+
+impl SampleOrder {
+    pub const NAMES: [&str; 4] = ["random", "overdue", "new", "weighted"];
+
+    pub fn parse(s: &str) -> Result<SampleOrder, String> {
+        match s {
+            "random" => Ok(SampleOrder::Random),
+            "overdue" => Ok(SampleOrder::MostOverdue),
+            "new" => Ok(SampleOrder::OldestNew),
+            "weighted" => Ok(SampleOrder::Weighted),
+            _ => Err(format!("unknown order of sampling: {s}")),
+        }
+    }
+}
+
+/// Parses quotas of tags, like `math=10` or `math=30%`.
+pub fn parse_quota(s: &str) -> Result<(String, Quota), String> {
+    let Some((tag, quota)) = s.split_once('=') else {
+        return Err(format!("no `=` in quota: {s}"));
+    };
+    let quota = match quota.strip_suffix('%') {
+        Some(x) => {
+            let x: f64 = x.parse().map_err(|_| format!("invalid quota: {s}"))?;
+            Quota::Share(x / 100.0)
+        }
+        None => Quota::Count(quota.parse().map_err(|_| format!("invalid quota: {s}"))?),
+    };
+    Ok((tag.to_string(), quota))
+}
+
+impl Sampling {
+    /// Takes at most `n` candidates, all without `n`, in the order of sampling.
+    ///
+    /// `item` tells the item of a candidate.
+    pub fn sample<'a, T>(
+        &self,
+        mut candidates: Vec<T>,
+        item: impl Fn(&T) -> &'a Item,
+        n: Option<usize>,
+        now: &UtcTime,
+    ) -> Vec<T> {
+        let total = candidates.len();
+        let mut rng = rand::rng();
+        match self.order {
+            SampleOrder::Random => candidates.shuffle(&mut rng),
+            SampleOrder::MostOverdue => {
+                // `None`, as of new items, is less than any `Some`.
+                candidates.sort_by_key(|x| (item(x).due_time.is_none(), item(x).due_time));
+            }
+            SampleOrder::OldestNew => {
+                candidates.sort_by_key(|x| (item(x).due_time.is_some(), item(x).due_time));
+            }
+            SampleOrder::Weighted => {
+                // weighted sampling without replacement, by Efraimidis and Spirakis
+                let mut keyed: Vec<_> = candidates
+                    .into_iter()
+                    .map(|x| {
+                        let weight = weight(item(&x), now);
+                        let key = rng.random::<f64>().powf(1.0 / weight);
+                        (key, x)
+                    })
+                    .collect();
+                keyed.sort_by(|a, b| b.0.total_cmp(&a.0));
+                candidates = keyed.into_iter().map(|(_, x)| x).collect();
+            }
+        }
+
+        let n = n.unwrap_or(total);
+        let limits: Vec<_> = self
+            .quotas
+            .iter()
+            .map(|(tag, quota)| {
+                let limit = match quota {
+                    Quota::Count(x) => *x,
+                    Quota::Share(x) => (x * n as f64).round() as usize,
+                };
+                (tag.as_str(), limit)
+            })
+            .collect();
+        let mut counts = vec![0; limits.len()];
+        let mut res = vec![];
+        for x in candidates.into_iter() {
+            if res.len() >= n {
+                break;
+            }
+            let tags: Vec<_> = item(&x)
+                .tag
+                .as_deref()
+                .map(|x| x.split(' ').collect())
+                .unwrap_or_default();
+            let quotas: Vec<_> = (0..limits.len())
+                .filter(|i| tags.contains(&limits[*i].0))
+                .collect();
+            if quotas.iter().any(|i| counts[*i] >= limits[*i].1) {
+                debug!("exclude item because of quotas: {}", item(&x).question);
+                continue;
+            }
+            for i in quotas.into_iter() {
+                counts[i] += 1;
+            }
+            res.push(x);
+        }
+        info!("{}/{} items sampled.", res.len(), total);
+        res
+    }
+}
+
+/// Weights of items in weighted sampling: 1 plus days overdue.
+fn weight(item: &Item, now: &UtcTime) -> f64 {
+    match item.due_time {
+        Some(due) if due < *now => 1.0 + (*now - due).num_seconds() as f64 / 86400.0,
+        _ => 1.0,
+    }
+}
@@ -1,81 +1,89 @@
 use crate::*;
 use chrono::prelude::*;
 use log::*;
-use rand::prelude::*;
-use std::{collections::*, path::PathBuf};
 
-#[derive(Debug)]
-pub struct Select {
-    /// the file to be selected from
-    pub input: PathBuf,
+pub struct Select<S: Store = Box<dyn Store>> {
+    /// the memory palace to be selected from
+    pub input: Workspace<S>,
 
-    /// the file to be wrote out
-    pub output: PathBuf,
+    /// the store to be appended
+    pub output: S,
 
     /// Selects at most N items of things.
     pub take: Option<usize>,
 
-    /// Selects only timed-out items
-    pub timeout: bool,
+    /// how to take items
+    pub sampling: Sampling,
 
-    /// Selects only items with one of the specified tags
-    pub tags: Option<HashSet<String>>,
+    /// Selects only items meeting the conditions
+    pub filter: Filter,
 
     /// Sorts the selected items.
     pub sort: bool,
+
+    /// Removes the selected items from the input, moving them to the output.
+    pub remove: bool,
+
+    /// Prints the selected items instead of writing.
+    pub dry_run: bool,
 }
 
-impl Select {
+impl<S: Store> Select<S> {
     pub fn gogogo(&self) {
-        let mut items = read_file(&self.input);
-        if self.timeout {
-            let now = Utc::now();
-            let retain_fn = |x: &Item| -> bool {
-                if let Some(due) = x.due_time {
-                    due < now
-                } else {
-                    true
-                }
-            };
-            items.retain(|x| {
-                let res = retain_fn(x);
-                if !res {
-                    debug!("exclude item because of not timing out: {}", x.question);
-                }
-                res
-            });
+        let now = Utc::now();
+        let mut items = self.input.read();
+        // (index of file, index of item in the file)
+        let picked: Vec<_> = items
+            .iter()
+            .enumerate()
+            .flat_map(|(f, xs)| {
+                xs.iter()
+                    .enumerate()
+                    .filter(|(_, x)| x.is_active(&now) && self.filter.matches(x, &now))
+                    .map(move |(i, _)| (f, i))
+            })
+            .collect();
+        let mut picked = self
+            .sampling
+            .sample(picked, |(f, i)| &items[*f][*i], self.take, &now);
+        if self.sort {
+            picked.sort_by_key(|(f, i)| &items[*f][*i].question);
+        } else if self.take.is_none() {
+            // Without taking, items are kept in their order in files.
+            picked.sort_unstable();
         }
-        if let Some(tags) = &self.tags {
-            let retain_fn = |x: &Item| -> bool {
-                let Some(item_tag) = &x.tag else {
-                    return false;
-                };
-                for x in item_tag.split(" ") {
-                    if tags.contains(x) {
-                        return true;
-                    }
-                }
-                false
-            };
-            items.retain(|x| {
-                let res = retain_fn(x);
-                if !res {
-                    debug!("exclude item because of no tags: {}", x.question);
-                }
-                res
-            });
+        if self.dry_run {
+            for (f, i) in picked.iter() {
+                println!(
+                    "{}\t{}",
+                    self.input.files[*f].path.display(),
+                    items[*f][*i].question
+                );
+            }
+            info!("dry run!");
+            return;
         }
-        if let Some(n) = self.take {
-            info!("shuffle and take {n} out of {} items.", items.len());
-            let mut rng = rand::rng();
-            items.shuffle(&mut rng);
-            items.truncate(n);
-        } else {
-            info!("take all {} items.", items.len());
+
+        let selected = picked.iter().map(|(f, i)| items[*f][*i].clone()).collect();
+        let appended = self.output.append(selected);
+        info!(
+            "{} items added, {} skipped and {} updated.",
+            appended.added, appended.skipped, appended.updated
+        );
+        if !self.remove {
+            return;
         }
-        if self.sort {
-            items.sort_by_key(|x| x.question.clone());
+        // Items are removed only after they are in the output,
+        // so nothing is lost if it fails in between.
+        picked.sort_unstable_by(|a, b| b.cmp(a));
+        for (f, i) in picked.iter() {
+            items[*f].remove(*i);
+        }
+        for (f, file) in self.input.files.iter().enumerate() {
+            if picked.iter().any(|(x, _)| *x == f) {
+                info!("remove moved items from {}", file.path.display());
+                file.write(&items[f]);
+            }
         }
-        append(&self.output, items);
     }
 }
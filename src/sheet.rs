@@ -0,0 +1,128 @@
+use crate::{file::WrapDatetime, *};
+use log::*;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// the hidden directory of sheet manifests, beside the printed palace.
+const SHEETS_DIR: &str = ".sheets";
+
+/// A manifest of a printed sheet: which items are printed, in which order.
+///
+/// It is kept beside the printed palace, so grades on the paper can be
+/// entered afterwards.
+#[derive(Debug, Clone)]
+pub struct Sheet {
+    pub id: String,
+    pub print_time: UtcTime,
+    /// the time items are checked at when printed, if so,
+    /// which grades count from instead of the time they are entered
+    pub check_time: Option<UtcTime>,
+    pub grade_time: Option<UtcTime>,
+    /// printed items in order, numbered from 1
+    pub items: Vec<SheetItem>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SheetItem {
+    /// the palace file where the item is
+    pub file: PathBuf,
+    pub question: String,
+}
+
+impl Sheet {
+    /// Reads the manifest of a sheet printed from `input`,
+    /// a file, a directory or a workspace manifest of a memory palace.
+    pub fn read(input: &Path, id: &str) -> Self {
+        let file_name = sheet_path(input, id);
+        debug!("read sheet {}", file_name.display());
+        let content = std::fs::read_to_string(&file_name).unwrap();
+        let sheet: SheetInDisk = toml::from_str(&content).unwrap();
+        Self {
+            id: id.to_string(),
+            print_time: WrapDatetime::from(sheet.print_time).0,
+            check_time: sheet.check_time.map(|x| WrapDatetime::from(x).0),
+            grade_time: sheet.grade_time.map(|x| WrapDatetime::from(x).0),
+            items: sheet
+                .items
+                .into_iter()
+                .map(|x| SheetItem {
+                    file: x.file,
+                    question: x.question,
+                })
+                .collect(),
+        }
+    }
+
+    /// Whether a sheet of the ID has been printed from `input`.
+    pub fn exists(input: &Path, id: &str) -> bool {
+        sheet_path(input, id).is_file()
+    }
+
+    /// Writes the manifest of a sheet printed from `input`.
+    ///
+    /// An existing one of the same ID is overwritten.
+    pub fn write(&self, input: &Path) {
+        let file_name = sheet_path(input, &self.id);
+        debug!("write sheet {}", file_name.display());
+        std::fs::create_dir_all(file_name.parent().unwrap()).unwrap();
+        let sheet = SheetInDisk {
+            print_time: WrapDatetime(self.print_time).into(),
+            check_time: self.check_time.map(|x| WrapDatetime(x).into()),
+            grade_time: self.grade_time.map(|x| WrapDatetime(x).into()),
+            items: self
+                .items
+                .iter()
+                .map(|x| SheetItemInDisk {
+                    file: x.file.clone(),
+                    question: x.question.clone(),
+                })
+                .collect(),
+        };
+        let content = toml::to_string_pretty(&sheet).unwrap();
+        std::fs::write(file_name, content).unwrap();
+    }
+}
+
+/// Parses IDs of sheets, which name files in the directory of sheets.
+/// IDs of sessions are parsed alike, as they name journals.
+pub fn parse_sheet_id(s: &str) -> Result<String, String> {
+    if s.is_empty() || s.starts_with('.') || s.contains(['/', '\\']) {
+        return Err(format!("invalid ID: {s}"));
+    }
+    Ok(s.to_string())
+}
+
+fn sheet_path(input: &Path, id: &str) -> PathBuf {
+    parse_sheet_id(id).unwrap();
+    let dir = if input.is_dir() {
+        input
+    } else {
+        match input.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        }
+    };
+    dir.join(SHEETS_DIR).join(format!("{}.toml", id))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SheetInDisk {
+    #[serde(rename = "print-time")]
+    print_time: toml::value::Datetime,
+    #[serde(
+        rename = "check-time",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    check_time: Option<toml::value::Datetime>,
+    #[serde(rename = "grade-time")]
+    grade_time: Option<toml::value::Datetime>,
+    items: Vec<SheetItemInDisk>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SheetItemInDisk {
+    file: PathBuf,
+    #[serde(rename = "q")]
+    question: String,
+}
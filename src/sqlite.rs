@@ -0,0 +1,424 @@
+use crate::*;
+use log::*;
+use rusqlite::{Connection, OpenFlags, Row, params};
+use std::{
+    collections::{HashMap, VecDeque},
+    path::{Path, PathBuf},
+};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS items (
+    id INTEGER PRIMARY KEY,
+    q TEXT NOT NULL,
+    a TEXT NOT NULL,
+    tag TEXT,
+    first_remember_time TEXT,
+    last_check_time TEXT,
+    due_time TEXT,
+    reviews INTEGER NOT NULL DEFAULT 0,
+    lapses INTEGER NOT NULL DEFAULT 0,
+    status TEXT,
+    buried_until TEXT);
+CREATE INDEX IF NOT EXISTS ix_items_q ON items (q);
+CREATE INDEX IF NOT EXISTS ix_items_due_time ON items (due_time);
+CREATE TABLE IF NOT EXISTS reviews (
+    q TEXT NOT NULL,
+    time TEXT NOT NULL,
+    correct INTEGER NOT NULL,
+    PRIMARY KEY (q, time));
+";
+
+const COLUMNS: &str = "q, a, tag, first_remember_time, last_check_time, due_time, \
+    reviews, lapses, status, buried_until";
+
+/// columns added to the items table since it is created, with their types,
+/// and values read from tables without them.
+const ADDED_COLUMNS: [(&str, &str, &str); 4] = [
+    ("reviews", "INTEGER NOT NULL DEFAULT 0", "0"),
+    ("lapses", "INTEGER NOT NULL DEFAULT 0", "0"),
+    ("status", "TEXT", "NULL"),
+    ("buried_until", "TEXT", "NULL"),
+];
+
+/// A memory palace in a SQLite database.
+///
+/// Saving writes only changed items in a transaction,
+/// and every check of an item is kept as a review.
+/// Tables are created or migrated only when items are written;
+/// reading never changes the database.
+#[derive(Debug, Clone)]
+pub struct SqliteStore {
+    pub path: PathBuf,
+}
+
+impl SqliteStore {
+    pub const EXTENSIONS: [&str; 2] = ["sqlite", "db"];
+
+    pub fn is_sqlite(file_name: &Path) -> bool {
+        file_name
+            .extension()
+            .and_then(|x| x.to_str())
+            .is_some_and(|x| Self::EXTENSIONS.contains(&x))
+    }
+
+    /// Whether the file is a database with a table of items.
+    pub fn has_items(file_name: &Path) -> bool {
+        let store = SqliteStore {
+            path: file_name.to_path_buf(),
+        };
+        store.open_read().is_some()
+    }
+
+    /// Reviews of an item in order, as check times and whether it is remembered.
+    pub fn reviews(&self, question: &str) -> Vec<(UtcTime, bool)> {
+        let Some(conn) = self.open_read() else {
+            return vec![];
+        };
+        if !has_table(&conn, "reviews") {
+            return vec![];
+        }
+        let mut stmt = conn
+            .prepare("SELECT time, correct FROM reviews WHERE q = ?1 ORDER BY time")
+            .unwrap();
+        stmt.query_map([question], |r| Ok((r.get(0)?, r.get(1)?)))
+            .unwrap()
+            .map(|x| x.unwrap())
+            .collect()
+    }
+
+    /// Opens the database to read, or nothing if it has no table of items.
+    fn open_read(&self) -> Option<Connection> {
+        if !self.path.is_file() {
+            return None;
+        }
+        let conn =
+            Connection::open_with_flags(&self.path, OpenFlags::SQLITE_OPEN_READ_ONLY).ok()?;
+        has_table(&conn, "items").then_some(conn)
+    }
+
+    /// Opens the database to write, creating or migrating tables.
+    fn open_write(&self) -> Connection {
+        let conn = Connection::open(&self.path).unwrap();
+        conn.execute_batch(SCHEMA).unwrap();
+        let columns = item_columns(&conn);
+        for (name, kind, _) in ADDED_COLUMNS.into_iter() {
+            if !columns.iter().any(|x| x == name) {
+                info!("add column {} to {}.", name, self.path.display());
+                conn.execute_batch(&format!("ALTER TABLE items ADD COLUMN {name} {kind}"))
+                    .unwrap();
+            }
+        }
+        conn
+    }
+}
+
+fn has_table(conn: &Connection, name: &str) -> bool {
+    // Files which are not databases fail here.
+    conn.query_row(
+        "SELECT count(*) FROM sqlite_master WHERE type = 'table' AND name = ?1",
+        [name],
+        |r| r.get::<_, i64>(0),
+    )
+    .is_ok_and(|n| n > 0)
+}
+
+fn item_columns(conn: &Connection) -> Vec<String> {
+    conn.prepare("SELECT name FROM pragma_table_info('items')")
+        .unwrap()
+        .query_map([], |r| r.get(0))
+        .unwrap()
+        .map(|x| x.unwrap())
+        .collect()
+}
+
+/// [`COLUMNS`] to read, where columns missing in old tables are given their values.
+fn select_columns(conn: &Connection) -> String {
+    let columns = item_columns(conn);
+    let mut res = "q, a, tag, first_remember_time, last_check_time, due_time".to_string();
+    for (name, _, missing) in ADDED_COLUMNS.into_iter() {
+        res.push_str(", ");
+        if columns.iter().any(|x| x == name) {
+            res.push_str(name);
+        } else {
+            res.push_str(missing);
+        }
+    }
+    res
+}
+
+impl Store for SqliteStore {
+    fn load(&self) -> Vec<Item> {
+        let Some(conn) = self.open_read() else {
+            debug!("no items in {}.", self.path.display());
+            return vec![];
+        };
+        let columns = select_columns(&conn);
+        let mut stmt = conn
+            .prepare(&format!("SELECT {columns} FROM items ORDER BY id"))
+            .unwrap();
+        let res: Vec<_> = stmt
+            .query_map([], |r| to_item(r, 0))
+            .unwrap()
+            .map(|x| x.unwrap())
+            .collect();
+        debug!("read {} items from {}.", res.len(), self.path.display());
+        res
+    }
+
+    fn save(&self, items: &[Item]) {
+        let mut conn = self.open_write();
+        let tx = conn.transaction().unwrap();
+        let mut olds: HashMap<String, VecDeque<(i64, Item)>> = HashMap::new();
+        {
+            let mut stmt = tx
+                .prepare(&format!("SELECT id, {COLUMNS} FROM items ORDER BY id"))
+                .unwrap();
+            let mut rows = stmt.query([]).unwrap();
+            while let Some(row) = rows.next().unwrap() {
+                let id: i64 = row.get(0).unwrap();
+                let item = to_item(row, 1).unwrap();
+                olds.entry(item.question.clone())
+                    .or_default()
+                    .push_back((id, item));
+            }
+        }
+        let (mut inserted, mut updated) = (0, 0);
+        for item in items.iter() {
+            match olds.get_mut(&item.question).and_then(|x| x.pop_front()) {
+                Some((_, old)) if &old == item => {}
+                Some((id, old)) => {
+                    tx.execute(
+                        "UPDATE items SET a = ?2, tag = ?3, first_remember_time = ?4,
+                         last_check_time = ?5, due_time = ?6, reviews = ?7, lapses = ?8,
+                         status = ?9, buried_until = ?10 WHERE id = ?1",
+                        params![
+                            id,
+                            item.answer,
+                            item.tag,
+                            item.first_remember_time,
+                            item.last_check_time,
+                            item.due_time,
+                            item.reviews,
+                            item.lapses,
+                            item.status.name(),
+                            item.status.buried_until()
+                        ],
+                    )
+                    .unwrap();
+                    if let Some(time) = item.last_check_time
+                        && old.last_check_time != Some(time)
+                    {
+                        add_review(
+                            &tx,
+                            &item.question,
+                            &time,
+                            item.first_remember_time.is_some(),
+                        );
+                    }
+                    updated += 1;
+                }
+                None => {
+                    insert(&tx, item);
+                    inserted += 1;
+                }
+            }
+        }
+        let mut deleted = 0;
+        for (id, _) in olds.into_values().flatten() {
+            tx.execute("DELETE FROM items WHERE id = ?1", [id]).unwrap();
+            deleted += 1;
+        }
+        tx.commit().unwrap();
+        debug!(
+            "{} items inserted, {} updated and {} deleted in {}.",
+            inserted,
+            updated,
+            deleted,
+            self.path.display()
+        );
+    }
+
+    fn update(&self, items: &[Item]) {
+        let mut conn = self.open_write();
+        let tx = conn.transaction().unwrap();
+        for item in items.iter() {
+            tx.execute(
+                "UPDATE items SET a = ?2, tag = ?3, first_remember_time = ?4,
+                 last_check_time = ?5, due_time = ?6, reviews = ?7, lapses = ?8,
+                 status = ?9, buried_until = ?10 WHERE q = ?1",
+                params![
+                    item.question,
+                    item.answer,
+                    item.tag,
+                    item.first_remember_time,
+                    item.last_check_time,
+                    item.due_time,
+                    item.reviews,
+                    item.lapses,
+                    item.status.name(),
+                    item.status.buried_until()
+                ],
+            )
+            .unwrap();
+            if let Some(time) = item.last_check_time {
+                add_review(
+                    &tx,
+                    &item.question,
+                    &time,
+                    item.first_remember_time.is_some(),
+                );
+            }
+        }
+        tx.commit().unwrap();
+        debug!("update {} items in {}.", items.len(), self.path.display());
+    }
+
+    fn due(&self, now: &UtcTime) -> Vec<Item> {
+        let Some(conn) = self.open_read() else {
+            return vec![];
+        };
+        let columns = select_columns(&conn);
+        // as `Item::is_due`, by the index of due times
+        let status = if item_columns(&conn).iter().any(|x| x == "status") {
+            "(status IS NULL OR status = 'active'
+              OR (status = 'buried' AND buried_until <= ?1))"
+        } else {
+            "1"
+        };
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT {columns} FROM items WHERE (due_time IS NULL OR due_time < ?1)
+                 AND {status} ORDER BY due_time, id"
+            ))
+            .unwrap();
+        stmt.query_map([now], |r| to_item(r, 0))
+            .unwrap()
+            .map(|x| x.unwrap())
+            .collect()
+    }
+}
+
+fn insert(conn: &Connection, item: &Item) {
+    conn.execute(
+        &format!("INSERT INTO items ({COLUMNS}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)"),
+        params![
+            item.question,
+            item.answer,
+            item.tag,
+            item.first_remember_time,
+            item.last_check_time,
+            item.due_time,
+            item.reviews,
+            item.lapses,
+            item.status.name(),
+            item.status.buried_until()
+        ],
+    )
+    .unwrap();
+}
+
+fn add_review(conn: &Connection, question: &str, time: &UtcTime, correct: bool) {
+    conn.execute(
+        "INSERT OR REPLACE INTO reviews VALUES (?1, ?2, ?3)",
+        params![question, time, correct],
+    )
+    .unwrap();
+}
+
+/// Reads an item from columns of a row since `start`.
+fn to_item(row: &Row, start: usize) -> rusqlite::Result<Item> {
+    Ok(Item {
+        question: row.get(start)?,
+        answer: row.get(start + 1)?,
+        tag: row.get(start + 2)?,
+        first_remember_time: row.get(start + 3)?,
+        last_check_time: row.get(start + 4)?,
+        due_time: row.get(start + 5)?,
+        reviews: row.get(start + 6)?,
+        lapses: row.get(start + 7)?,
+        status: Status::from_parts(
+            row.get::<_, Option<String>>(start + 8)?.as_deref(),
+            row.get(start + 9)?,
+        ),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::prelude::*;
+
+    fn item(question: &str, due_time: Option<UtcTime>, status: Status) -> Item {
+        Item {
+            due_time,
+            status,
+            ..Item::new(question, "A.")
+        }
+    }
+
+    fn temp_db(name: &str) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("memory-palace-{}-{}.db", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn read_old_tables_without_changes() {
+        let path = temp_db("old");
+        let conn = Connection::open(&path).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE items (id INTEGER PRIMARY KEY, q TEXT NOT NULL, a TEXT NOT NULL,
+                tag TEXT, first_remember_time TEXT, last_check_time TEXT, due_time TEXT);
+             INSERT INTO items (q, a) VALUES ('Q?', 'A.');",
+        )
+        .unwrap();
+        drop(conn);
+        let store = SqliteStore { path: path.clone() };
+        assert_eq!(store.load(), vec![item("Q?", None, Status::Active)]);
+        assert_eq!(store.due(&Utc::now()).len(), 1);
+        assert!(!item_columns(&store.open_read().unwrap()).contains(&"reviews".to_string()));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn read_other_databases_as_empty() {
+        let path = temp_db("other");
+        let conn = Connection::open(&path).unwrap();
+        conn.execute_batch("CREATE TABLE notes (x TEXT);").unwrap();
+        drop(conn);
+        assert!(!SqliteStore::has_items(&path));
+        let store = SqliteStore { path: path.clone() };
+        assert!(store.load().is_empty());
+        assert!(!SqliteStore::has_items(&path));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn due_items() {
+        let path = temp_db("due");
+        let now = Utc.with_ymd_and_hms(2024, 1, 2, 3, 4, 5).unwrap();
+        let day = chrono::Duration::days(1);
+        let items = vec![
+            item("later", Some(now + day), Status::Active),
+            item("due", Some(now - day), Status::Active),
+            item("new", None, Status::Active),
+            item("suspended", Some(now - day), Status::Suspended),
+            item("buried", Some(now - day), Status::Buried(now + day)),
+            item("unburied", Some(now - day * 2), Status::Buried(now - day)),
+        ];
+        let store = SqliteStore { path: path.clone() };
+        store.save(&items);
+        let questions = |xs: Vec<Item>| xs.into_iter().map(|x| x.question).collect::<Vec<_>>();
+        let expected = vec!["new", "unburied", "due"];
+        assert_eq!(questions(store.due(&now)), expected);
+        // the same as the default of stores
+        let file = FileStore {
+            path: path.with_extension("json"),
+        };
+        file.save(&items);
+        assert_eq!(questions(file.due(&now)), expected);
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(&file.path).unwrap();
+    }
+}
@@ -0,0 +1,92 @@
+use crate::*;
+use chrono::prelude::*;
+use std::path::PathBuf;
+
+#[derive(Debug)]
+pub struct Stats {
+    /// the files, directories or workspace manifests of memory palaces
+    pub inputs: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Counter {
+    total: usize,
+    due: usize,
+    new: usize,
+    leech: usize,
+    /// items out of reviews
+    inactive: usize,
+}
+
+impl Stats {
+    pub fn gogogo(&self) {
+        let workspace = Workspace::open(&self.inputs);
+        let now = Utc::now();
+        let mut sum = Counter::default();
+        // (lapses, question, file)
+        let mut leeches = vec![];
+        println!(
+            "{:>8} {:>8} {:>8} {:>8} {:>8}  file",
+            "total", "due", "new", "leech", "inactive"
+        );
+        for (f, items) in workspace.files.iter().zip(workspace.read().iter()) {
+            let counter = count(items, &f.scheduler, &now);
+            println!(
+                "{:>8} {:>8} {:>8} {:>8} {:>8}  {}",
+                counter.total,
+                counter.due,
+                counter.new,
+                counter.leech,
+                counter.inactive,
+                f.path.display()
+            );
+            sum.total += counter.total;
+            sum.due += counter.due;
+            sum.new += counter.new;
+            sum.leech += counter.leech;
+            sum.inactive += counter.inactive;
+            leeches.extend(
+                items
+                    .iter()
+                    .filter(|x| x.is_leech(&f.scheduler))
+                    .map(|x| (x.lapses, x.question.clone(), f.path.display())),
+            );
+        }
+        if workspace.files.len() > 1 {
+            println!(
+                "{:>8} {:>8} {:>8} {:>8} {:>8}  (all)",
+                sum.total, sum.due, sum.new, sum.leech, sum.inactive
+            );
+        }
+        if !leeches.is_empty() {
+            leeches.sort_by_key(|x| std::cmp::Reverse(x.0));
+            println!();
+            println!("{:>8}  leech", "lapses");
+            for (lapses, question, path) in leeches.iter() {
+                println!("{:>8}  {}  ({})", lapses, question, path);
+            }
+        }
+    }
+}
+
+fn count(items: &[Item], scheduler: &Scheduler, now: &UtcTime) -> Counter {
+    let mut res = Counter {
+        total: items.len(),
+        ..Counter::default()
+    };
+    for item in items.iter() {
+        if item.is_leech(scheduler) {
+            res.leech += 1;
+        }
+        if !item.is_active(now) {
+            res.inactive += 1;
+            continue;
+        }
+        if item.due_time.is_none() {
+            res.new += 1;
+        } else if item.is_due(now) {
+            res.due += 1;
+        }
+    }
+    res
+}
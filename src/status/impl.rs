@@ -0,0 +1,61 @@
+use crate::*;
+use chrono::prelude::*;
+use log::*;
+
+/// Changes the status of items, taking them in or out of reviews.
+pub struct SetStatus<S: Store = Box<dyn Store>> {
+    /// the memory palaces whose items are changed
+    pub workspace: Workspace<S>,
+
+    pub status: Status,
+
+    /// Changes only items of the questions, which identify items
+    pub ids: Vec<String>,
+
+    /// Changes only items meeting the conditions
+    pub filter: Filter,
+
+    /// Prints the items to be changed without writing.
+    pub dry_run: bool,
+}
+
+impl<S: Store> SetStatus<S> {
+    pub fn gogogo(&self) {
+        let now = Utc::now();
+        let mut items = self.workspace.read();
+        let mut found = vec![false; self.ids.len()];
+        let mut changed = 0;
+        for (f, items) in self.workspace.files.iter().zip(items.iter_mut()) {
+            let mut changed_in_file = 0;
+            for item in items.iter_mut() {
+                let id = self.ids.iter().position(|x| *x == item.question);
+                if let Some(i) = id {
+                    found[i] = true;
+                }
+                if (!self.ids.is_empty() && id.is_none()) || !self.filter.matches(item, &now) {
+                    continue;
+                }
+                if item.status == self.status {
+                    debug!("unchanged: {}", item.question);
+                    continue;
+                }
+                if self.dry_run {
+                    println!("{}\t{}", f.path.display(), item.question);
+                }
+                item.status = self.status;
+                changed_in_file += 1;
+            }
+            if changed_in_file > 0 && !self.dry_run {
+                f.write(items);
+            }
+            changed += changed_in_file;
+        }
+        for (id, _) in self.ids.iter().zip(found.iter()).filter(|x| !x.1) {
+            warn!("no item of the question: {}", id);
+        }
+        info!("{} items {}.", changed, self.status.name());
+        if self.dry_run {
+            info!("dry run!");
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use crate::*;
+use std::{cell::RefCell, path::Path};
+
+/// Where items of a memory palace are kept.
+///
+/// Files are stores by [`open_store`], and others, like databases of an
+/// application embedding memory palaces, can be plugged in by implementing it.
+pub trait Store {
+    fn load(&self) -> Vec<Item>;
+
+    fn save(&self, items: &[Item]);
+
+    /// Appends items, merging ones of existing questions as [`Deck::append`].
+    fn append(&self, items: Vec<Item>) -> Appended {
+        let mut deck = Deck::load(self);
+        let res = deck.append(items);
+        self.save(&deck.items);
+        res
+    }
+
+    /// Replaces items of the same questions, after they are checked.
+    ///
+    /// Stores which update items in place should override it.
+    fn update(&self, items: &[Item]) {
+        let mut all = self.load();
+        for item in items.iter() {
+            for x in all.iter_mut().filter(|x| x.question == item.question) {
+                *x = item.clone();
+            }
+        }
+        self.save(&all);
+    }
+
+    /// Items to review now: new ones, and ones due before now, unless out of
+    /// reviews, as [`Deck::due`]. New ones come first, and then the earliest due.
+    ///
+    /// Stores which find them without loading every item should override it.
+    fn due(&self, now: &UtcTime) -> Vec<Item> {
+        let mut res: Vec<_> = self.load().into_iter().filter(|x| x.is_due(now)).collect();
+        res.sort_by_key(|x| x.due_time);
+        res
+    }
+}
+
+/// Opens the store of a file, told by its extension.
+///
+/// `.sqlite` and `.db` files are SQLite databases, and others are text files.
+pub fn open_store(file_name: &Path) -> Box<dyn Store> {
+    if SqliteStore::is_sqlite(file_name) {
+        Box::new(SqliteStore {
+            path: file_name.to_path_buf(),
+        })
+    } else {
+        Box::new(FileStore {
+            path: file_name.to_path_buf(),
+        })
+    }
+}
+
+impl<S: Store + ?Sized> Store for Box<S> {
+    fn load(&self) -> Vec<Item> {
+        (**self).load()
+    }
+
+    fn save(&self, items: &[Item]) {
+        (**self).save(items)
+    }
+
+    fn append(&self, items: Vec<Item>) -> Appended {
+        (**self).append(items)
+    }
+
+    fn update(&self, items: &[Item]) {
+        (**self).update(items)
+    }
+
+    fn due(&self, now: &UtcTime) -> Vec<Item> {
+        (**self).due(now)
+    }
+}
+
+/// A memory palace in memory.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryStore {
+    pub items: RefCell<Vec<Item>>,
+}
+
+impl MemoryStore {
+    pub fn new(items: Vec<Item>) -> Self {
+        Self {
+            items: RefCell::new(items),
+        }
+    }
+}
+
+impl Store for MemoryStore {
+    fn load(&self) -> Vec<Item> {
+        self.items.borrow().clone()
+    }
+
+    fn save(&self, items: &[Item]) {
+        *self.items.borrow_mut() = items.to_vec();
+    }
+}
@@ -0,0 +1,191 @@
+use crate::{merge::*, *};
+use log::*;
+use std::{
+    collections::{BTreeSet, HashMap},
+    path::{Path, PathBuf},
+};
+
+/// Synchronizes memory palaces with their copies in a shared directory.
+///
+/// Both sides get the same items afterwards.
+/// Besides every local file, a hidden file keeps items of the last
+/// synchronization, which tells what are changed or deleted on either side.
+/// It is in the format of the local file, or in JSON beside databases,
+/// so items are kept as precisely as in the palace.
+pub struct Synchronize {
+    /// the files, directories or workspace manifests of local memory palaces
+    pub locals: Vec<PathBuf>,
+
+    /// the shared directory where copies of memory palaces are
+    pub shared: PathBuf,
+}
+
+impl Synchronize {
+    pub fn gogogo(&self) {
+        if !self.shared.is_dir() {
+            error!("no shared directory: {}", self.shared.display());
+            return;
+        }
+        let workspace = Workspace::open(&self.locals);
+        let mut names = BTreeSet::new();
+        for f in workspace.files.iter() {
+            let name = f.path.file_name().unwrap();
+            if !names.insert(name.to_owned()) {
+                warn!("skip file of duplicate name: {}", f.path.display());
+                continue;
+            }
+            sync_file(f, &self.shared.join(name));
+        }
+    }
+}
+
+/// the file of items of the last synchronization, and its format.
+fn base_path(local: &Path) -> (PathBuf, FileFormat) {
+    let format = FileFormat::of(local).unwrap_or(FileFormat::Json);
+    let name = local.file_name().unwrap().to_string_lossy();
+    let path = local.with_file_name(format!(".{}.sync-base.{}", name, format.extension()));
+    (path, format)
+}
+
+fn sync_file(local: &PalaceFile, shared: &Path) {
+    let (base, format) = base_path(&local.path);
+    // The copy gets and loses default tags as the local file.
+    let shared = PalaceFile {
+        path: shared.to_path_buf(),
+        tags: local.tags.clone(),
+        scheduler: local.scheduler.clone(),
+        limits: DailyLimits::default(),
+        state: None,
+        store: open_store(shared),
+    };
+    let items_local = local.read();
+    let (items_shared, items_base) = if shared.path.is_file() {
+        let items_base = if base.is_file() {
+            read_file_as(&base, format)
+        } else {
+            vec![]
+        };
+        (shared.read(), items_base)
+    } else {
+        info!("new shared copy: {}", shared.path.display());
+        (vec![], vec![])
+    };
+
+    let (Some(map_local), Some(map_shared), Some(map_base)) = (
+        by_question(&items_local),
+        by_question(&items_shared),
+        by_question(&items_base),
+    ) else {
+        warn!(
+            "skip because of duplicate questions: {}",
+            local.path.display()
+        );
+        return;
+    };
+    let questions: BTreeSet<_> = map_local.keys().chain(map_shared.keys()).copied().collect();
+    let mut res = vec![];
+    for q in questions.into_iter() {
+        let base = map_base.get(q).copied();
+        match (map_local.get(q).copied(), map_shared.get(q).copied()) {
+            (Some(l), Some(s)) => match merge(l, s, base) {
+                Merged::Unchanged => res.push(l.clone()),
+                Merged::Changed(x) => res.push(x),
+                Merged::Conflict(x, fields) => {
+                    warn!(
+                        "conflict on {}, keep the local one: {}\n{:?}",
+                        fields.join(", "),
+                        q,
+                        s
+                    );
+                    res.push(x);
+                }
+            },
+            (Some(x), None) | (None, Some(x)) => match base {
+                Some(base) if base == x => {
+                    debug!("deleted: {}", q);
+                }
+                Some(_) => {
+                    warn!(
+                        "keep the item changed on one side but deleted on the other: {}",
+                        q
+                    );
+                    res.push(x.clone());
+                }
+                None => {
+                    debug!("added: {}", q);
+                    res.push(x.clone());
+                }
+            },
+            (None, None) => unreachable!(),
+        }
+    }
+
+    info!(
+        "synchronize {} with {}: {} items.",
+        local.path.display(),
+        shared.path.display(),
+        res.len()
+    );
+    local.write(&res);
+    shared.write(&res);
+    write_out_as(&base, format, &res);
+}
+
+fn by_question(items: &[Item]) -> Option<HashMap<&str, &Item>> {
+    let mut res = HashMap::new();
+    for item in items.iter() {
+        if res.insert(item.question.as_str(), item).is_some() {
+            warn!("duplicate question: {}", item.question);
+            return None;
+        }
+    }
+    Some(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::prelude::*;
+
+    fn item(question: &str, check: UtcTime) -> Item {
+        Item {
+            first_remember_time: Some(check),
+            last_check_time: Some(check),
+            due_time: Some(check + chrono::Duration::days(1)),
+            reviews: 1,
+            ..Item::new(question, "A.")
+        }
+    }
+
+    #[test]
+    fn sync_json() {
+        let dir = std::env::temp_dir().join(format!("memory-palace-sync-{}", std::process::id()));
+        let shared = dir.join("shared");
+        std::fs::create_dir_all(&shared).unwrap();
+        let path = dir.join("a.json");
+        let local = PalaceFile {
+            path: path.clone(),
+            tags: None,
+            scheduler: Scheduler::default(),
+            limits: DailyLimits::default(),
+            state: None,
+            store: open_store(&path),
+        };
+        // times with fractions of seconds, which JSON keeps
+        let check = Utc.with_ymd_and_hms(2024, 1, 2, 3, 4, 5).unwrap()
+            + chrono::Duration::nanoseconds(123_456_789);
+        let items = vec![item("Q1?", check), item("Q2?", check)];
+        local.write(&items);
+
+        sync_file(&local, &shared.join("a.json"));
+        assert_eq!(read_file(&shared.join("a.json")), items);
+        assert_eq!(local.read(), items);
+
+        // An item deleted in the shared copy is deleted locally.
+        write_out(&shared.join("a.json"), &items[1..]);
+        sync_file(&local, &shared.join("a.json"));
+        assert_eq!(local.read(), items[1..]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,231 @@
+use crate::{file::fmt_time, *};
+use log::*;
+use std::path::Path;
+
+const BOM: &str = "\u{feff}";
+
+/// Fields of items which columns of tables hold.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Column {
+    Question,
+    Answer,
+    Tag,
+    FirstRememberTime,
+    LastCheckTime,
+    DueTime,
+    Reviews,
+    Lapses,
+    Status,
+    BuriedUntil,
+    /// a column to be ignored
+    Skip,
+}
+
+/// How items are laid out in CSV or TSV files.
+#[derive(Debug, Clone)]
+pub struct TableOptions {
+    /// `b','` for CSV, or `b'\t'` for TSV
+    pub delimiter: u8,
+
+    /// fields of columns in order.
+    /// Without them, columns are told by the header on reading,
+    /// or are all fields on writing.
+    pub columns: Option<Vec<Column>>,
+
+    /// Whether the first row is the header.
+    pub header: bool,
+
+    /// Writes a UTF-8 BOM, which some spreadsheets require.
+    pub bom: bool,
+}
+
+impl Default for TableOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: b',',
+            columns: None,
+            header: true,
+            bom: false,
+        }
+    }
+}
+
+impl Column {
+    pub const ALL: [Column; 10] = [
+        Column::Question,
+        Column::Answer,
+        Column::Tag,
+        Column::FirstRememberTime,
+        Column::LastCheckTime,
+        Column::DueTime,
+        Column::Reviews,
+        Column::Lapses,
+        Column::Status,
+        Column::BuriedUntil,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Column::Question => "q",
+            Column::Answer => "a",
+            Column::Tag => "tag",
+            Column::FirstRememberTime => "first-remember-time",
+            Column::LastCheckTime => "last-check-time",
+            Column::DueTime => "due-time",
+            Column::Reviews => "reviews",
+            Column::Lapses => "lapses",
+            Column::Status => "status",
+            Column::BuriedUntil => "buried-until",
+            Column::Skip => "-",
+        }
+    }
+
+    /// Parses names of columns, like `q`, `question`, `due-time`, or `-` to skip.
+    pub fn parse(s: &str) -> Result<Column, String> {
+        match s.trim().to_lowercase().as_str() {
+            "q" | "question" => Ok(Column::Question),
+            "a" | "answer" => Ok(Column::Answer),
+            "tag" | "tags" => Ok(Column::Tag),
+            "first-remember-time" => Ok(Column::FirstRememberTime),
+            "last-check-time" => Ok(Column::LastCheckTime),
+            "due-time" => Ok(Column::DueTime),
+            "reviews" => Ok(Column::Reviews),
+            "lapses" => Ok(Column::Lapses),
+            "status" => Ok(Column::Status),
+            "buried-until" => Ok(Column::BuriedUntil),
+            "-" | "" => Ok(Column::Skip),
+            _ => Err(format!("unknown column: {s}")),
+        }
+    }
+}
+
+/// Reads items from a CSV or TSV file.
+pub fn read_table(file_name: &Path, opts: &TableOptions) -> Vec<Item> {
+    let content = std::fs::read_to_string(file_name).unwrap();
+    let content = content.strip_prefix(BOM).unwrap_or(&content);
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(opts.delimiter)
+        .has_headers(opts.header)
+        .flexible(true)
+        .from_reader(content.as_bytes());
+    let columns = match &opts.columns {
+        Some(columns) => columns.clone(),
+        None if opts.header => reader
+            .headers()
+            .unwrap()
+            .iter()
+            .map(|x| {
+                Column::parse(x).unwrap_or_else(|_| {
+                    debug!("ignore column: {}", x);
+                    Column::Skip
+                })
+            })
+            .collect(),
+        None => vec![Column::Question, Column::Answer, Column::Tag],
+    };
+    let mut res = vec![];
+    for record in reader.records() {
+        let record = record.unwrap();
+        let mut item = Item {
+            question: String::new(),
+            answer: String::new(),
+            first_remember_time: None,
+            last_check_time: None,
+            due_time: None,
+            tag: None,
+            reviews: 0,
+            lapses: 0,
+            status: Status::Active,
+        };
+        let (mut status, mut buried_until) = (None, None);
+        for (column, value) in columns.iter().zip(record.iter()) {
+            let value = value.trim();
+            match column {
+                Column::Question => item.question = value.to_string(),
+                Column::Answer => item.answer = value.to_string(),
+                Column::Tag if !value.is_empty() => item.tag = Some(value.to_string()),
+                Column::FirstRememberTime => item.first_remember_time = parse_time(value),
+                Column::LastCheckTime => item.last_check_time = parse_time(value),
+                Column::DueTime => item.due_time = parse_time(value),
+                Column::Reviews => item.reviews = parse_count(value),
+                Column::Lapses => item.lapses = parse_count(value),
+                Column::Status if !value.is_empty() => status = Some(value),
+                Column::BuriedUntil => buried_until = parse_time(value),
+                _ => {}
+            }
+        }
+        item.status = Status::from_parts(status, buried_until);
+        if item.question.is_empty() {
+            warn!(
+                "skip a row without questions at line {:?}.",
+                record.position().map(|x| x.line())
+            );
+            continue;
+        }
+        res.push(item);
+    }
+    debug!("read {} items from {}.", res.len(), file_name.display());
+    res
+}
+
+/// Writes items into a CSV or TSV file.
+pub fn write_table(file_name: &Path, items: &[Item], opts: &TableOptions) {
+    debug!("write {} items into {}.", items.len(), file_name.display());
+    let columns = match &opts.columns {
+        Some(columns) => columns.clone(),
+        None => Column::ALL.to_vec(),
+    };
+    let mut buf = vec![];
+    if opts.bom {
+        buf.extend_from_slice(BOM.as_bytes());
+    }
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(opts.delimiter)
+        .from_writer(buf);
+    if opts.header {
+        writer
+            .write_record(columns.iter().map(|x| x.name()))
+            .unwrap();
+    }
+    for item in items.iter() {
+        let record = columns.iter().map(|x| match x {
+            Column::Question => item.question.clone(),
+            Column::Answer => item.answer.clone(),
+            Column::Tag => item.tag.clone().unwrap_or_default(),
+            Column::FirstRememberTime => fmt_time(&item.first_remember_time),
+            Column::LastCheckTime => fmt_time(&item.last_check_time),
+            Column::DueTime => fmt_time(&item.due_time),
+            Column::Reviews => item.reviews.to_string(),
+            Column::Lapses => item.lapses.to_string(),
+            Column::Status => item.status.name().to_string(),
+            Column::BuriedUntil => fmt_time(&item.status.buried_until()),
+            Column::Skip => String::new(),
+        });
+        writer.write_record(record).unwrap();
+    }
+    let buf = writer.into_inner().unwrap();
+    std::fs::write(file_name, buf).unwrap();
+}
+
+fn parse_time(s: &str) -> Option<UtcTime> {
+    if s.is_empty() {
+        return None;
+    }
+    match chrono::DateTime::parse_from_rfc3339(s) {
+        Ok(x) => Some(x.to_utc()),
+        Err(e) => {
+            warn!("ignore an invalid time {}: {}", s, e);
+            None
+        }
+    }
+}
+
+fn parse_count(s: &str) -> u32 {
+    if s.is_empty() {
+        return 0;
+    }
+    s.parse().unwrap_or_else(|e| {
+        warn!("ignore an invalid count {}: {}", s, e);
+        0
+    })
+}
@@ -0,0 +1,179 @@
+use crate::*;
+use chrono::prelude::*;
+use log::*;
+
+/// Restores items touched in an exam session as they were before it.
+pub struct Undo<S: Store = Box<dyn Store>> {
+    /// the memory palaces examined
+    pub workspace: Workspace<S>,
+
+    /// the ID of the session, or the last session not undone yet
+    pub session: Option<String>,
+
+    /// Lists sessions instead of undoing.
+    pub list: bool,
+
+    /// Prints the items to be restored without writing.
+    pub dry_run: bool,
+}
+
+impl<S: Store> Undo<S> {
+    pub fn gogogo(&self) {
+        let workspace = &self.workspace;
+        if self.list {
+            for id in Session::list(workspace).into_iter() {
+                let session = Session::read(workspace, &id);
+                match session.undo_time {
+                    Some(t) => println!("{}\t{} items\tundone at {}", id, session.items.len(), t),
+                    None => println!("{}\t{} items", id, session.items.len()),
+                }
+            }
+            return;
+        }
+        let mut session = match &self.session {
+            Some(id) => Session::read(workspace, id),
+            None => {
+                let last = Session::list(workspace)
+                    .into_iter()
+                    .rev()
+                    .map(|id| Session::read(workspace, &id))
+                    .find(|x| x.undo_time.is_none());
+                let Some(last) = last else {
+                    error!("no sessions to undo.");
+                    return;
+                };
+                last
+            }
+        };
+        if let Some(t) = session.undo_time {
+            error!("session {} has been undone at {}.", session.id, t);
+            return;
+        }
+
+        let files: Vec<_> = workspace
+            .files
+            .iter()
+            .map(|x| std::fs::canonicalize(&x.path).unwrap())
+            .collect();
+        let mut items = workspace.read();
+        let mut changed = vec![false; files.len()];
+        let mut restored = 0;
+        for x in session.items.iter() {
+            let Some(f) = files.iter().position(|f| *f == x.file) else {
+                warn!("No file for item: {}", x.file.display());
+                continue;
+            };
+            let Some(item) = items[f]
+                .iter_mut()
+                .find(|y| y.question == x.before.question)
+            else {
+                warn!("No item: {}", x.before.question);
+                continue;
+            };
+            if !is_same(item, &x.after) {
+                warn!("skip an item changed since the session: {}", item.question);
+                continue;
+            }
+            if self.dry_run {
+                println!("{}\t{}", workspace.files[f].path.display(), item.question);
+            }
+            *item = x.before.clone();
+            changed[f] = true;
+            restored += 1;
+        }
+        info!(
+            "{}/{} items of session {} restored.",
+            restored,
+            session.items.len(),
+            session.id
+        );
+
+        if self.dry_run {
+            info!("dry run!");
+            return;
+        }
+        if restored == 0 {
+            warn!("nothing to restore, and session {} is kept.", session.id);
+            return;
+        }
+        for ((f, items), changed) in workspace.files.iter().zip(items.iter()).zip(changed) {
+            if changed {
+                f.write(items);
+            }
+        }
+        session.undo_time = Some(Utc::now());
+        session.write(workspace);
+    }
+}
+
+/// Whether items are the same, where times are compared to the second,
+/// as they are kept in TOML files.
+fn is_same(a: &Item, b: &Item) -> bool {
+    to_seconds(a) == to_seconds(b)
+}
+
+fn to_seconds(item: &Item) -> Item {
+    let trunc = |x: Option<UtcTime>| x.map(|x| x.with_nanosecond(0).unwrap());
+    Item {
+        first_remember_time: trunc(item.first_remember_time),
+        last_check_time: trunc(item.last_check_time),
+        due_time: trunc(item.due_time),
+        status: match item.status {
+            Status::Buried(until) => Status::Buried(trunc(Some(until)).unwrap()),
+            x => x,
+        },
+        ..item.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn undo_sessions() {
+        for ext in ["toml", "json"] {
+            undo_session_of(ext);
+        }
+    }
+
+    fn undo_session_of(ext: &str) {
+        let dir =
+            std::env::temp_dir().join(format!("memory-palace-undo-{}-{}", ext, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(format!("a.{ext}"));
+        let before = Item::new("Q?", "A.");
+        write_out(&path, std::slice::from_ref(&before));
+
+        // checked in a session, at a time with fractions of seconds
+        let now = Utc.with_ymd_and_hms(2024, 1, 2, 3, 4, 5).unwrap()
+            + chrono::Duration::nanoseconds(123_456_789);
+        let mut after = before.clone();
+        after.correct(&now);
+        let workspace = Workspace::open(std::slice::from_ref(&path));
+        workspace.write(&[vec![after.clone()]]);
+        Session::new(
+            &workspace,
+            now,
+            vec![SessionItem {
+                file: std::fs::canonicalize(&path).unwrap(),
+                before: before.clone(),
+                after,
+            }],
+        )
+        .write(&workspace);
+
+        let undo = Undo {
+            workspace,
+            session: None,
+            list: false,
+            dry_run: false,
+        };
+        undo.gogogo();
+        assert_eq!(read_file(&path), vec![before]);
+        let id = Session::list(&undo.workspace).pop().unwrap();
+        assert!(Session::read(&undo.workspace, &id).undo_time.is_some());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,47 +1,91 @@
-use crate::{Item, file::*};
+use crate::{Deck, Item, Store, Workspace, merge::*};
 use log::*;
-use std::path::PathBuf;
 
-pub struct Update {
-    pub into: Vec<PathBuf>,
-    pub from: PathBuf,
+pub struct Update<S: Store = Box<dyn Store>> {
+    /// the memory palaces to be merged into
+    pub into: Workspace<S>,
+
+    /// the store to merge from
+    pub from: S,
+
+    /// the store of items before they are changed on either side
+    pub base: Option<S>,
+
+    /// Prints planned changes as a diff instead of writing back.
+    pub dry_run: bool,
+
+    /// Removes merged items from `from`, leaving only the rest there.
+    /// Otherwise `from` is untouched.
+    pub remove_merged: bool,
 }
 
-impl Update {
+impl<S: Store> Update<S> {
     pub fn gogogo(self) {
-        let mut items_into: Vec<_> = self.into.iter().map(|f| read_file(f)).collect();
+        let workspace = &self.into;
+        let mut decks: Vec<_> = workspace.files.iter().map(|x| x.deck()).collect();
 
-        let items_from = read_file(&self.from);
+        let items_from = self.from.load();
+        let items_base = self.base.as_ref().map(|x| x.load());
         let mut remains = vec![];
+        let mut updated = 0;
         for item in items_from.into_iter() {
-            if let Some(dest) = find_position(&mut items_into, &item) {
-                *dest = item;
-            } else {
+            let Some(f) = find_deck(&decks, &item) else {
                 remains.push(item);
+                continue;
+            };
+            let dest = decks[f].get(&item.question).unwrap().clone();
+            // Without a base, contents of `from` win over the destination.
+            let base = match &items_base {
+                Some(xs) => xs.iter().find(|x| x.question == item.question),
+                None => Some(&dest),
+            };
+            match decks[f].merge_item(&item, base).unwrap() {
+                Merged::Unchanged => {
+                    debug!("unchanged: {}", item.question);
+                }
+                Merged::Changed(merged) => {
+                    if self.dry_run {
+                        let path = workspace.files[f].path.display();
+                        println!("--- {}", path);
+                        println!("+++ {}", path);
+                        println!("@@ q = {:?} @@", item.question);
+                        print!("{}", diff(&dest, &merged));
+                    }
+                    updated += 1;
+                }
+                Merged::Conflict(_, fields) => {
+                    warn!("conflict on {}: {}", fields.join(", "), item.question);
+                    remains.push(item);
+                }
             }
         }
+        info!("{} items updated, {} items remain.", updated, remains.len());
 
-        for (f, items) in self.into.iter().zip(items_into.iter()) {
-            debug!("write to {}", f.display());
-            write_out(f, items);
+        if self.dry_run {
+            info!("dry run!");
+            return;
+        }
+        for (f, deck) in workspace.files.iter().zip(decks.iter()) {
+            debug!("write to {}", f.path.display());
+            f.write(&deck.items);
+        }
+        if self.remove_merged {
+            self.from.save(&remains);
         }
-        debug!("write to {}", self.from.display());
-        write_out(&self.from, &remains);
     }
 }
 
-fn find_position<'a>(items_into: &'a mut [Vec<Item>], item: &Item) -> Option<&'a mut Item> {
+/// the index of the deck with the only item of the same question.
+fn find_deck(decks: &[Deck], item: &Item) -> Option<usize> {
     let question = &item.question;
     let mut res = None;
-    for items in items_into.iter_mut() {
-        for item_in_items in items.iter_mut() {
-            if &item_in_items.question == question {
-                if res.is_none() {
-                    res = Some(item_in_items);
-                } else {
-                    warn!("duplicate question: {}", question);
-                    return None;
-                }
+    for (f, deck) in decks.iter().enumerate() {
+        for _ in deck.items.iter().filter(|x| &x.question == question) {
+            if res.is_none() {
+                res = Some(f);
+            } else {
+                warn!("duplicate question: {}", question);
+                return None;
             }
         }
     }
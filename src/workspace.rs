@@ -0,0 +1,422 @@
+use crate::*;
+use log::*;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+/// the name of the manifest of a workspace.
+pub const MANIFEST: &str = "palace.toml";
+
+/// A set of memory-palace files.
+///
+/// It is given by files, directories, or manifests of workspaces.
+/// Every palace file under a directory is discovered,
+/// and the manifest in the directory, if any, provides default tags and
+/// scheduler settings of files.
+/// A subdirectory with its own manifest is opened as a directory on its own.
+///
+/// Items of every file are kept in a [`Store`], files by default.
+///
+/// Daily limits of exams are given by the manifest of every directory,
+/// and counted in the [`STATE_FILE`] beside the manifest, or beside files
+/// given on their own.
+pub struct Workspace<S: Store = Box<dyn Store>> {
+    pub files: Vec<PalaceFile<S>>,
+
+    /// the daily state of the first file or manifest, if any,
+    /// beside which sessions are journaled
+    pub state: Option<PathBuf>,
+}
+
+pub struct PalaceFile<S: Store = Box<dyn Store>> {
+    /// the file, or the name of the store elsewhere
+    pub path: PathBuf,
+
+    /// tags of items which are not tagged.
+    pub tags: Option<String>,
+
+    pub scheduler: Scheduler,
+
+    /// daily limits of exams, shared by files of the same manifest
+    pub limits: DailyLimits,
+
+    /// the file of the daily state, shared by files of the same manifest
+    pub state: Option<PathBuf>,
+
+    pub store: S,
+}
+
+impl Workspace {
+    pub fn open(paths: &[PathBuf]) -> Self {
+        let mut res = Self {
+            files: vec![],
+            state: None,
+        };
+        for path in paths.iter() {
+            if path.is_dir() {
+                res.open_dir(path);
+            } else if path.file_name().is_some_and(|x| x == MANIFEST) {
+                let dir = match path.parent() {
+                    Some(dir) if !dir.as_os_str().is_empty() => dir,
+                    _ => Path::new("."),
+                };
+                res.open_dir(dir);
+            } else {
+                let dir = match path.parent() {
+                    Some(dir) if !dir.as_os_str().is_empty() => dir,
+                    _ => Path::new("."),
+                };
+                res.push(PalaceFile {
+                    path: path.clone(),
+                    tags: None,
+                    scheduler: Scheduler::default(),
+                    limits: DailyLimits::default(),
+                    state: Some(dir.join(STATE_FILE)),
+                    store: open_store(path),
+                });
+            }
+        }
+        res
+    }
+
+    fn open_dir(&mut self, dir: &Path) {
+        let manifest = read_manifest(&dir.join(MANIFEST));
+        let boundary = manifest.boundary();
+        let default_scheduler = manifest.scheduler.to_scheduler(&Scheduler {
+            boundary,
+            ..Scheduler::default()
+        });
+        let limits = manifest.limits.to_limits(boundary);
+        let state = Some(dir.join(STATE_FILE));
+        let mut paths = vec![];
+        let mut nested = vec![];
+        discover(dir, &mut paths, &mut nested);
+        paths.sort();
+        for path in paths.into_iter() {
+            let in_manifest = manifest.files.iter().find(|x| dir.join(&x.path) == path);
+            let file = match in_manifest {
+                Some(x) => PalaceFile {
+                    store: open_store(&path),
+                    path,
+                    tags: x.tags.clone(),
+                    scheduler: x.scheduler.to_scheduler(&default_scheduler),
+                    limits: limits.clone(),
+                    state: state.clone(),
+                },
+                None => PalaceFile {
+                    store: open_store(&path),
+                    path,
+                    tags: None,
+                    scheduler: default_scheduler.clone(),
+                    limits: limits.clone(),
+                    state: state.clone(),
+                },
+            };
+            self.push(file);
+        }
+        for x in manifest.files.iter() {
+            let path = dir.join(&x.path);
+            if path.is_file() {
+                // files out of the directory, hidden ones, or databases without items yet
+                self.push(PalaceFile {
+                    store: open_store(&path),
+                    path,
+                    tags: x.tags.clone(),
+                    scheduler: x.scheduler.to_scheduler(&default_scheduler),
+                    limits: limits.clone(),
+                    state: state.clone(),
+                });
+            } else {
+                warn!("missing file in {}: {}", MANIFEST, x.path.display());
+            }
+        }
+        for dir in nested.iter() {
+            self.open_dir(dir);
+        }
+    }
+
+    fn push(&mut self, file: PalaceFile) {
+        if self.files.iter().any(|x| x.path == file.path) {
+            return;
+        }
+        if self.state.is_none() {
+            self.state = file.state.clone();
+        }
+        debug!("palace file: {}", file.path.display());
+        self.files.push(file);
+    }
+}
+
+impl<S: Store> Workspace<S> {
+    /// A workspace of stores, given with their names and default tags.
+    pub fn from_stores(stores: Vec<(PathBuf, Option<String>, S)>) -> Self {
+        Self {
+            files: stores
+                .into_iter()
+                .map(|(path, tags, store)| PalaceFile {
+                    path,
+                    tags,
+                    scheduler: Scheduler::default(),
+                    limits: DailyLimits::default(),
+                    state: None,
+                    store,
+                })
+                .collect(),
+            state: None,
+        }
+    }
+
+    pub fn paths(&self) -> Vec<PathBuf> {
+        self.files.iter().map(|x| x.path.clone()).collect()
+    }
+
+    /// Reads items of all files, in the order of files.
+    pub fn read(&self) -> Vec<Vec<Item>> {
+        self.files.iter().map(|x| x.read()).collect()
+    }
+
+    /// Reads items of all files into a deck.
+    pub fn deck(&self) -> Deck {
+        Deck::new(self.read().into_iter().flatten().collect())
+    }
+
+    /// Writes items back to files, in the order of files.
+    pub fn write(&self, items: &[Vec<Item>]) {
+        for (f, items) in self.files.iter().zip(items.iter()) {
+            f.write(items);
+        }
+    }
+
+    /// Updates checked items in files, in the order of files.
+    pub fn update(&self, items: &[Vec<Item>]) {
+        for (f, items) in self.files.iter().zip(items.iter()) {
+            if !items.is_empty() {
+                f.update(items);
+            }
+        }
+    }
+}
+
+impl<S: Store> PalaceFile<S> {
+    /// Reads items, where items without tags get the default tags of the file.
+    pub fn read(&self) -> Vec<Item> {
+        self.tag(self.store.load())
+    }
+
+    /// Reads items to review now, as [`Store::due`], into a deck as `deck`.
+    pub fn due(&self, now: &UtcTime) -> Deck {
+        Deck {
+            items: self.tag(self.store.due(now)),
+            scheduler: self.scheduler.clone(),
+        }
+    }
+
+    /// Reads items into a deck, scheduled as the file.
+    pub fn deck(&self) -> Deck {
+        Deck {
+            items: self.read(),
+            scheduler: self.scheduler.clone(),
+        }
+    }
+
+    /// Writes items, where items with exactly the default tags get no tags.
+    pub fn write(&self, items: &[Item]) {
+        self.store.save(&self.untag(items));
+    }
+
+    /// Updates checked items in the store, as `write` does.
+    pub fn update(&self, items: &[Item]) {
+        self.store.update(&self.untag(items));
+    }
+
+    fn tag(&self, mut items: Vec<Item>) -> Vec<Item> {
+        if let Some(tags) = &self.tags {
+            for item in items.iter_mut() {
+                if item.tag.is_none() {
+                    item.tag = Some(tags.clone());
+                }
+            }
+        }
+        items
+    }
+
+    fn untag(&self, items: &[Item]) -> Vec<Item> {
+        let Some(tags) = &self.tags else {
+            return items.to_vec();
+        };
+        items
+            .iter()
+            .cloned()
+            .map(|mut x| {
+                if x.tag.as_ref() == Some(tags) {
+                    x.tag = None;
+                }
+                x
+            })
+            .collect()
+    }
+}
+
+/// Parses durations like `30m`, `12h`, `3d` or `2w`.
+pub fn parse_duration(s: &str) -> Result<chrono::Duration, String> {
+    let s = s.trim();
+    let Some(unit) = s.chars().last() else {
+        return Err("empty duration".to_string());
+    };
+    let n: i64 = s[..s.len() - unit.len_utf8()]
+        .parse()
+        .map_err(|_| format!("invalid duration: {s}"))?;
+    if n < 0 {
+        return Err(format!("negative duration: {s}"));
+    }
+    match unit {
+        's' => Ok(chrono::Duration::seconds(n)),
+        'm' => Ok(chrono::Duration::minutes(n)),
+        'h' => Ok(chrono::Duration::hours(n)),
+        'd' => Ok(chrono::Duration::days(n)),
+        'w' => Ok(chrono::Duration::weeks(n)),
+        _ => Err(format!("unknown unit of duration: {s}")),
+    }
+}
+
+/// Discovers palace files under `dir`, and directories with their own manifests.
+fn discover(dir: &Path, paths: &mut Vec<PathBuf>, nested: &mut Vec<PathBuf>) {
+    let mut entries: Vec<_> = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|x| x.unwrap().path())
+        .collect();
+    entries.sort();
+    for path in entries.into_iter() {
+        let Some(name) = path.file_name().and_then(|x| x.to_str()) else {
+            continue;
+        };
+        if name.starts_with('.') || name == MANIFEST {
+            continue;
+        }
+        if path.is_dir() {
+            if path.join(MANIFEST).is_file() {
+                nested.push(path);
+            } else {
+                discover(&path, paths, nested);
+            }
+            continue;
+        }
+        let Some((stem, ext)) = name.rsplit_once('.') else {
+            continue;
+        };
+        if stem.ends_with(".bak") {
+            debug!("skip a backup: {}", path.display());
+        } else if FileFormat::EXTENSIONS.contains(&ext) {
+            // Files like `Cargo.toml` or `package.json` are not palaces.
+            if is_palace_file(&path) {
+                paths.push(path);
+            } else {
+                debug!("skip a file which is not a palace: {}", path.display());
+            }
+        } else if SqliteStore::EXTENSIONS.contains(&ext) {
+            // Other databases are left alone, unless listed in the manifest.
+            if SqliteStore::has_items(&path) {
+                paths.push(path);
+            } else {
+                debug!("skip a database which is not a palace: {}", path.display());
+            }
+        }
+    }
+}
+
+fn read_manifest(file_name: &Path) -> Manifest {
+    if !file_name.is_file() {
+        return Manifest::default();
+    }
+    debug!("read manifest {}", file_name.display());
+    let content = std::fs::read_to_string(file_name).unwrap();
+    toml::from_str(&content).unwrap()
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+struct Manifest {
+    /// the time zone of the user, like `Asia/Tokyo`, or the one of the system
+    #[serde(rename = "time-zone")]
+    time_zone: Option<String>,
+    /// the local hour when a day begins
+    #[serde(rename = "day-starts-at")]
+    day_starts_at: Option<u32>,
+    scheduler: SchedulerInDisk,
+    limits: LimitsInDisk,
+    files: Vec<FileInManifest>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct FileInManifest {
+    path: PathBuf,
+    tags: Option<String>,
+    #[serde(default)]
+    scheduler: SchedulerInDisk,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+struct SchedulerInDisk {
+    #[serde(rename = "init-duration")]
+    init_duration: Option<String>,
+    #[serde(rename = "max-duration")]
+    max_duration: Option<String>,
+    #[serde(rename = "leech-lapses")]
+    leech_lapses: Option<u32>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+struct LimitsInDisk {
+    #[serde(rename = "new-per-day")]
+    new_per_day: Option<usize>,
+    #[serde(rename = "reviews-per-day")]
+    reviews_per_day: Option<usize>,
+}
+
+impl Manifest {
+    fn boundary(&self) -> DayBoundary {
+        let starts_at = self.day_starts_at.unwrap_or(0);
+        assert!(starts_at < 24, "invalid day-starts-at: {starts_at}");
+        DayBoundary {
+            zone: self.time_zone.as_ref().and_then(|x| parse_zone(x).unwrap()),
+            starts_at,
+        }
+    }
+}
+
+impl LimitsInDisk {
+    fn to_limits(&self, boundary: DayBoundary) -> DailyLimits {
+        DailyLimits {
+            new_per_day: self.new_per_day,
+            reviews_per_day: self.reviews_per_day,
+            boundary,
+        }
+    }
+}
+
+impl SchedulerInDisk {
+    fn to_scheduler(&self, default: &Scheduler) -> Scheduler {
+        let parse = |name: &str, x: &String| {
+            let res = parse_duration(x).unwrap();
+            assert!(
+                res >= MIN_DURATION,
+                "{name} must be at least {}s: {x}",
+                MIN_DURATION.num_seconds()
+            );
+            res
+        };
+        Scheduler {
+            init_duration: self
+                .init_duration
+                .as_ref()
+                .map(|x| parse("init-duration", x))
+                .unwrap_or(default.init_duration),
+            max_duration: self
+                .max_duration
+                .as_ref()
+                .map(|x| parse("max-duration", x))
+                .unwrap_or(default.max_duration),
+            boundary: default.boundary,
+            leech_lapses: self.leech_lapses.unwrap_or(default.leech_lapses),
+        }
+    }
+}